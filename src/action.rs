@@ -0,0 +1,1568 @@
+//! Execution of configured actions against a window backend. The executor is shared by
+//! everything that can trigger an action, so it must not assume it is being driven by a
+//! hotkey.
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::backend::{Backend, MonitorId, MonitorInfo, WindowId, WindowInfo};
+use crate::config::{
+    Action, Config, Direction, Fraction, IgnoreConfig, LayoutConfig, MonitorTarget, Rule,
+    RuleAction, TilingConfig, WindowBehaviour,
+};
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::ignore::{self, IgnoreReason};
+use crate::rules;
+use crate::snapshot::{self, SavedWindow};
+use crate::window::{Gaps, Rect};
+use crate::workspace::{Transition, Workspaces};
+
+/// The smallest width or height that a clamped resize will shrink a window to.
+pub const MINIMUM_WINDOW_SIZE: i32 = 100;
+
+/// The window an action applies to, along with the geometry it is computed against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionContext {
+    pub window: WindowId,
+    pub monitor: MonitorId,
+    pub window_rect: Rect,
+    /// The work area of the monitor the window is on.
+    pub work_area: Rect,
+    /// Every monitor on the desktop, in the order given by
+    /// [`crate::backend::sort_monitors`].
+    pub monitors: Vec<MonitorInfo>,
+    /// The gaps to leave around windows placed in the work area.
+    pub gaps: Gaps,
+}
+
+impl ActionContext {
+    pub fn for_window<B: Backend>(
+        backend: &B,
+        window: WindowId,
+        layout: &LayoutConfig,
+    ) -> Result<ActionContext> {
+        let window_rect = backend.window_rect(window)?;
+        let monitor = backend.window_monitor(window)?;
+        let work_area = backend.monitor_work_area(monitor)?;
+        let monitors = backend.monitors()?;
+        let gaps = layout.resolve(&work_area);
+
+        Ok(ActionContext {
+            window,
+            monitor,
+            window_rect,
+            work_area,
+            monitors,
+            gaps,
+        })
+    }
+
+    /// The full bounds of the monitor the window is on.
+    pub fn monitor_bounds(&self) -> Rect {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.id == self.monitor)
+            .map_or(self.work_area, |monitor| monitor.bounds)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// There was no window to act on.
+    NoFocusedWindow,
+    /// The window is already where the action would put it.
+    Unchanged,
+    /// There is no monitor matching the action's target.
+    NoTargetMonitor,
+    /// There is nothing to undo, redo or restore for the window.
+    NoHistory,
+    /// The window is one that whimsy leaves alone.
+    Ignored(IgnoreReason),
+    /// There is no saved layout with the requested name.
+    NoSuchLayout,
+    /// There is no window in the requested direction.
+    NoWindowInDirection,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActionOutcome {
+    Moved {
+        window: WindowId,
+        from: Rect,
+        to: Rect,
+    },
+    /// Every window on a monitor was arranged by a tiling layout.
+    Tiled {
+        monitor: MonitorId,
+        /// The windows that were moved. Windows that were already in place are left out.
+        moved: Vec<WindowId>,
+    },
+    /// The position of every window was saved under a name.
+    LayoutSaved {
+        name: String,
+        windows: usize,
+    },
+    /// Windows were moved back to where they were when a layout was saved.
+    LayoutRestored {
+        name: String,
+        /// The windows that were moved. Windows that were already in place are left out.
+        moved: Vec<WindowId>,
+    },
+    Focused {
+        from: WindowId,
+        to: WindowId,
+    },
+    /// Windows were hidden and shown to change workspaces.
+    WorkspaceChanged {
+        monitor: MonitorId,
+        workspace: u32,
+        hidden: Vec<WindowId>,
+        shown: Vec<WindowId>,
+    },
+    Skipped(SkipReason),
+}
+
+/// Picks the rect nearest to `from` in a direction, returning its index in `candidates`.
+/// Only rects whose centers lie in that direction count. For the cardinal directions, rects
+/// that overlap `from` across the direction (that are level with it, when going left or
+/// right) are preferred. Ties are broken by the distance between centers, penalizing
+/// distance off to the side.
+pub fn select_neighbour(from: &Rect, candidates: &[Rect], direction: Direction) -> Option<usize> {
+    let (x, y) = from.center();
+    let (cx, cy) = direction.components();
+
+    candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(index, candidate)| {
+            let (candidate_x, candidate_y) = candidate.center();
+            let (dx, dy) = ((candidate_x - x) as i64, (candidate_y - y) as i64);
+            let (cx, cy) = (cx as i64, cy as i64);
+
+            // Diagonal directions need the center to lie in both directions.
+            let in_direction = (cx == 0 || cx * dx > 0) && (cy == 0 || cy * dy > 0);
+            if !in_direction {
+                return None;
+            }
+
+            let overlaps = match (cx, cy) {
+                (0, _) => candidate.left < from.right && from.left < candidate.right,
+                (_, 0) => candidate.top < from.bottom && from.top < candidate.bottom,
+                _ => false,
+            };
+
+            let along = cx * dx + cy * dy;
+            let across = (cy * dx - cx * dy).abs();
+            Some((index, !overlaps, along + across * 2))
+        })
+        .min_by_key(|&(_, not_overlapping, score)| (not_overlapping, score))
+        .map(|(index, _, _)| index)
+}
+
+/// Picks the monitor that a [`MonitorTarget`] refers to, relative to the monitor at
+/// `current` in `monitors`. Returns `None` if there is no such monitor, or if the target is
+/// the current monitor.
+pub fn select_monitor(
+    monitors: &[MonitorInfo],
+    current: usize,
+    target: MonitorTarget,
+) -> Option<usize> {
+    let count = monitors.len();
+    let selected = match target {
+        MonitorTarget::Next => (current + 1) % count,
+        MonitorTarget::Previous => (current + count - 1) % count,
+        MonitorTarget::Index(index) if index < count => index,
+        MonitorTarget::Index(_) => return None,
+        MonitorTarget::Direction(direction) => {
+            let from = monitors[current].bounds;
+            let (x, y) = from.center();
+
+            monitors
+                .iter()
+                .enumerate()
+                .filter_map(|(index, monitor)| {
+                    let bounds = monitor.bounds;
+                    let (mx, my) = bounds.center();
+                    let (dx, dy) = ((mx - x) as i64, (my - y) as i64);
+                    let (cx, cy) = direction.components();
+
+                    // Only monitors entirely past the current monitor's edges in the
+                    // direction count. Among those, prefer the closest, penalizing monitors
+                    // that are off to the side.
+                    let beyond_horizontally = match cx {
+                        0 => true,
+                        1 => bounds.left >= from.right,
+                        _ => bounds.right <= from.left,
+                    };
+                    let beyond_vertically = match cy {
+                        0 => true,
+                        1 => bounds.top >= from.bottom,
+                        _ => bounds.bottom <= from.top,
+                    };
+                    let beyond = beyond_horizontally && beyond_vertically;
+
+                    let (cx, cy) = (cx as i64, cy as i64);
+                    let along = cx * dx + cy * dy;
+                    let across = (cy * dx - cx * dy).abs();
+
+                    if beyond {
+                        Some((index, along + across * 2))
+                    } else {
+                        None
+                    }
+                })
+                .min_by_key(|&(_, score)| score)
+                .map(|(index, _)| index)?
+        }
+    };
+
+    if selected == current {
+        None
+    } else {
+        Some(selected)
+    }
+}
+
+/// Computes the rect that an action will move a window to. This does not touch the backend,
+/// and does not modify the history.
+pub fn target_rect(
+    action: &Action,
+    context: &ActionContext,
+    history: &History,
+) -> std::result::Result<Rect, SkipReason> {
+    // Placements are made inside the outer gaps, and leave room for the inner gaps.
+    let area = context.gaps.placement_area(&context.work_area);
+    let place = |rect: Rect| rect.apply_inner_gaps(&area, &context.gaps);
+
+    let rect = match *action {
+        Action::Push {
+            direction,
+            ref fraction,
+        } => place(area.slice_rect(direction, fraction.step(0))),
+        Action::Nudge {
+            direction,
+            distance,
+        } => {
+            let (width, height) = context.window_rect.wh();
+            let (x, y) = direction.components();
+            context
+                .window_rect
+                .offset(x * distance.resolve(width), y * distance.resolve(height))
+        }
+        Action::Grid {
+            columns,
+            rows,
+            x,
+            y,
+            width,
+            height,
+        } => place(area.grid_span(columns, rows, x, y, width, height)),
+        Action::Resize { edge, delta, clamp } => {
+            let (width, height) = context.window_rect.wh();
+            // Even unclamped, a window cannot be turned inside out.
+            let (limit, min_size) = if clamp {
+                (Some(&area), MINIMUM_WINDOW_SIZE)
+            } else {
+                (None, 1)
+            };
+
+            let (vertical, horizontal) = edge.split();
+            let mut rect = context.window_rect;
+            if let Some(vertical) = vertical {
+                rect = rect.resize_edge(vertical, delta.resolve(height), limit, min_size);
+            }
+            if let Some(horizontal) = horizontal {
+                rect = rect.resize_edge(horizontal, delta.resolve(width), limit, min_size);
+            }
+
+            rect
+        }
+        Action::MoveToMonitor { target } => {
+            let current = context
+                .monitors
+                .iter()
+                .position(|monitor| monitor.id == context.monitor)
+                .ok_or(SkipReason::NoTargetMonitor)?;
+            let selected = select_monitor(&context.monitors, current, target)
+                .ok_or(SkipReason::NoTargetMonitor)?;
+
+            context
+                .window_rect
+                .map_between(&context.work_area, &context.monitors[selected].work_area)
+        }
+        Action::Center { width, height } => {
+            let (area_width, area_height) = area.wh();
+            area.centered(width.resolve(area_width), height.resolve(area_height))
+        }
+        Action::Maximize => area,
+        Action::AlmostMaximize { margin } => {
+            let (width, height) = area.wh();
+            area.inset(margin.resolve(width), margin.resolve(height))
+        }
+        Action::Undo => history
+            .peek_undo(context.window)
+            .ok_or(SkipReason::NoHistory)?,
+        Action::Redo => history
+            .peek_redo(context.window)
+            .ok_or(SkipReason::NoHistory)?,
+        Action::Restore => history
+            .original(context.window)
+            .ok_or(SkipReason::NoHistory)?,
+        // These move or look at every window, which the executor takes care of, or only
+        // change which bindings are active. On their own, the window stays where it is.
+        Action::Tile
+        | Action::SaveLayout { .. }
+        | Action::RestoreLayout { .. }
+        | Action::SwitchWorkspace(_)
+        | Action::MoveToWorkspace(_)
+        | Action::Focus { .. }
+        | Action::EnterMode { .. }
+        | Action::ExitMode => context.window_rect,
+    };
+
+    Ok(rect)
+}
+
+/// The last step of a cycling push that was applied to a window.
+#[derive(Debug, Clone)]
+struct CycleStep {
+    action: Action,
+    step: usize,
+    /// The rect the window was moved to.
+    rect: Rect,
+}
+
+pub struct ActionExecutor<B> {
+    backend: B,
+    history: History,
+    cycles: HashMap<WindowId, CycleStep>,
+    layout: LayoutConfig,
+    tiling: TilingConfig,
+    /// Windows in the order they are given to tiling layouts. Windows are added to the end
+    /// the first time they are tiled, so retiling keeps windows in the same slots.
+    tiling_order: Vec<WindowId>,
+    rules: Vec<Rule>,
+    ignore: IgnoreConfig,
+    /// Windows that rules have already been applied to, so that a window that is hidden
+    /// and shown again is not moved again.
+    seen_windows: HashSet<WindowId>,
+    /// Where saved layouts are kept.
+    snapshot_path: PathBuf,
+    workspaces: Workspaces,
+}
+
+impl<B: Backend> ActionExecutor<B> {
+    pub fn new(backend: B) -> ActionExecutor<B> {
+        ActionExecutor {
+            backend,
+            history: History::default(),
+            cycles: HashMap::new(),
+            layout: LayoutConfig::default(),
+            tiling: TilingConfig::default(),
+            tiling_order: Vec::new(),
+            rules: Vec::new(),
+            ignore: IgnoreConfig::default(),
+            seen_windows: HashSet::new(),
+            snapshot_path: snapshot::DEFAULT_SNAPSHOT_PATH.clone(),
+            workspaces: Workspaces::new(),
+        }
+    }
+
+    /// Picks up the parts of a configuration that affect how actions are executed.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.layout = config.layout.clone();
+        self.tiling = config.tiling.clone();
+        self.rules = config.rules.clone();
+        self.ignore = config.ignore.clone();
+    }
+
+    pub fn set_snapshot_path(&mut self, path: PathBuf) {
+        self.snapshot_path = path;
+    }
+
+    pub fn tiling(&self) -> &TilingConfig {
+        &self.tiling
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Updates the history once an action has moved a window away from `from`.
+    fn commit_history(&mut self, action: &Action, window: WindowId, from: Rect) {
+        match action {
+            Action::Undo => {
+                self.history.undo(window, from);
+            }
+            Action::Redo => {
+                self.history.redo(window, from);
+            }
+            _ => self.history.record(window, from),
+        }
+    }
+
+    /// Forgets the history of windows that no longer exist.
+    fn prune_history(&mut self) {
+        let backend = &self.backend;
+        self.history.retain(|window| backend.window_exists(window));
+        self.cycles
+            .retain(|&window, _| backend.window_exists(window));
+        self.tiling_order
+            .retain(|&window| backend.window_exists(window));
+        self.seen_windows
+            .retain(|&window| backend.window_exists(window));
+        self.workspaces
+            .retain(|window| backend.window_exists(window));
+    }
+
+    /// Computes where an action will move a window, along with the step of the cycle it used
+    /// if it is a cycling push.
+    fn resolve_target(
+        &self,
+        action: &Action,
+        context: &ActionContext,
+    ) -> std::result::Result<(Rect, usize), SkipReason> {
+        let (direction, fraction) = match action {
+            Action::Push {
+                direction,
+                fraction,
+            } if fraction.len() > 1 => (*direction, fraction),
+            _ => return target_rect(action, context, &self.history).map(|rect| (rect, 0)),
+        };
+
+        // Steps that would leave the window where it already is are passed over, so that
+        // every press visibly does something.
+        let first_step = self.cycle_step(action, context);
+        let mut unchanged = None;
+
+        for offset in 0..fraction.len() {
+            let step = (first_step + offset) % fraction.len();
+            let resolved_action = Action::Push {
+                direction,
+                fraction: Fraction::Single(fraction.step(step)),
+            };
+            let rect = target_rect(&resolved_action, context, &self.history)?;
+
+            if rect != context.window_rect {
+                return Ok((rect, step));
+            }
+
+            unchanged.get_or_insert((rect, step));
+        }
+
+        Ok(unchanged.unwrap_or((context.window_rect, first_step)))
+    }
+
+    /// Works out which step of a cycling push to apply. Pressing the same push again
+    /// advances to the next step, as long as the window has not been moved since the last
+    /// step was applied; otherwise the cycle starts over.
+    fn cycle_step(&self, action: &Action, context: &ActionContext) -> usize {
+        let fraction = match action {
+            Action::Push { fraction, .. } => fraction,
+            _ => return 0,
+        };
+
+        match self.cycles.get(&context.window) {
+            Some(last) if last.action == *action && last.rect == context.window_rect => {
+                (last.step + 1) % fraction.len()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Executes an action against the focused window.
+    pub fn execute(&mut self, action: &Action) -> Result<ActionOutcome> {
+        self.prune_history();
+        if let Some(outcome) = self.execute_on_desktop(action) {
+            return outcome;
+        }
+
+        match self.backend.focused_window() {
+            Some(window) => self.execute_on_window(action, window),
+            None => Ok(ActionOutcome::Skipped(SkipReason::NoFocusedWindow)),
+        }
+    }
+
+    /// Executes an action against a specific window.
+    pub fn execute_on_window(
+        &mut self,
+        action: &Action,
+        window: WindowId,
+    ) -> Result<ActionOutcome> {
+        self.prune_history();
+        if let Some(outcome) = self.execute_on_desktop(action) {
+            return outcome;
+        }
+
+        let context = ActionContext::for_window(&self.backend, window, &self.layout)?;
+
+        let ignored =
+            self.ignore_reason(window, &context.window_rect, &context.monitor_bounds())?;
+        if let Some(reason) = ignored {
+            log::info!(
+                "Not executing {:?} on window {:?} because {}",
+                action,
+                window,
+                reason
+            );
+            return Ok(ActionOutcome::Skipped(SkipReason::Ignored(reason)));
+        }
+
+        self.execute_in_context(action, &context)
+    }
+
+    /// Returns the first rule that matches a window.
+    fn matching_rule(&self, window: WindowId) -> Result<Option<&Rule>> {
+        if self.rules.is_empty() {
+            return Ok(None);
+        }
+
+        let info = self.backend.window_info(window)?;
+        Ok(rules::find_rule(&self.rules, &info))
+    }
+
+    /// Returns why a window should be left alone, if it should.
+    fn ignore_reason(
+        &self,
+        window: WindowId,
+        window_rect: &Rect,
+        monitor_bounds: &Rect,
+    ) -> Result<Option<IgnoreReason>> {
+        let info = self.backend.window_info(window)?;
+        let rule = rules::find_rule(&self.rules, &info);
+
+        if let Some(Rule {
+            action: RuleAction::Behaviour(WindowBehaviour::Ignore),
+            ..
+        }) = rule
+        {
+            return Ok(Some(IgnoreReason::Rule));
+        }
+
+        Ok(ignore::ignore_reason(
+            &self.ignore,
+            &info,
+            window_rect,
+            monitor_bounds,
+        ))
+    }
+
+    /// Returns the special treatment that rules give a window, if any.
+    fn window_behaviour(&self, window: WindowId) -> Result<Option<WindowBehaviour>> {
+        Ok(match self.matching_rule(window)? {
+            Some(Rule {
+                action: RuleAction::Behaviour(behaviour),
+                ..
+            }) => Some(*behaviour),
+            _ => None,
+        })
+    }
+
+    /// Handles a window appearing on the desktop. The first time a window appears, the
+    /// first rule that matches it is applied. If tiling is enabled, the window's monitor is
+    /// then retiled.
+    pub fn window_shown(&mut self, window: WindowId) -> Result<Vec<ActionOutcome>> {
+        self.prune_history();
+        if !self.backend.windows()?.contains(&window) {
+            return Ok(Vec::new());
+        }
+
+        let mut outcomes = Vec::new();
+        let behaviour = self.window_behaviour(window)?;
+
+        if self.seen_windows.insert(window) {
+            let rule_action = match self.matching_rule(window)? {
+                Some(Rule {
+                    action: RuleAction::Action(action),
+                    ..
+                }) => Some(action.clone()),
+                _ => None,
+            };
+
+            if let Some(action) = rule_action {
+                log::debug!("Applying rule action {:?} to window {:?}", action, window);
+                outcomes.push(self.execute_on_window(&action, window)?);
+            }
+        }
+
+        if self.tiling.enabled && behaviour.is_none() {
+            let monitor = self.backend.window_monitor(window)?;
+            outcomes.push(self.tile_monitor(monitor)?);
+        }
+
+        Ok(outcomes)
+    }
+
+    /// Handles a window leaving the desktop, because it was hidden, minimized or closed. If
+    /// the window was tiled and tiling is enabled, the windows left behind are retiled to
+    /// fill the space it leaves.
+    pub fn window_hidden(&mut self, window: WindowId) -> Result<Vec<ActionOutcome>> {
+        // The window may no longer exist, so it is not known which monitor it was on.
+        if !self.tiling.enabled || !self.tiling_order.contains(&window) {
+            return Ok(Vec::new());
+        }
+
+        self.tile_all()
+    }
+
+    /// Handles a window being destroyed. Its history is forgotten straight away, since the
+    /// handle may be reused by a new window, and the windows it leaves behind are retiled as
+    /// for [`window_hidden`](Self::window_hidden).
+    pub fn window_destroyed(&mut self, window: WindowId) -> Result<Vec<ActionOutcome>> {
+        self.history.forget(window);
+        self.cycles.remove(&window);
+        self.window_hidden(window)
+    }
+
+    /// Executes an action using precomputed window and monitor geometry.
+    pub fn execute_in_context(
+        &mut self,
+        action: &Action,
+        context: &ActionContext,
+    ) -> Result<ActionOutcome> {
+        match *action {
+            Action::Tile => return self.tile_monitor(context.monitor),
+            Action::MoveToWorkspace(workspace) => {
+                return self.move_to_workspace(context.window, context.monitor, workspace)
+            }
+            Action::Focus { direction } => {
+                return self.focus_in_direction(context.window, &context.window_rect, direction)
+            }
+            _ => {}
+        }
+
+        if let Some(outcome) = self.execute_on_desktop(action) {
+            return outcome;
+        }
+
+        let from = context.window_rect;
+        let (to, step) = match self.resolve_target(action, context) {
+            Ok(target) => target,
+            Err(reason) => {
+                log::debug!("Skipping {:?}: {:?}", action, reason);
+                return Ok(ActionOutcome::Skipped(reason));
+            }
+        };
+
+        if from == to {
+            // An undo or redo step that would not move the window is still consumed, so
+            // that the next press gets to a step that does.
+            if let Action::Undo | Action::Redo = action {
+                self.commit_history(action, context.window, from);
+            }
+
+            log::debug!(
+                "Window {:?} is already at {:?}, skipping {:?}",
+                context.window,
+                to,
+                action
+            );
+            return Ok(ActionOutcome::Skipped(SkipReason::Unchanged));
+        }
+
+        self.backend.set_window_rect(context.window, to)?;
+        self.commit_history(action, context.window, from);
+
+        if let Action::Push { fraction, .. } = action {
+            if fraction.len() > 1 {
+                self.cycles.insert(
+                    context.window,
+                    CycleStep {
+                        action: action.clone(),
+                        step,
+                        rect: to,
+                    },
+                );
+            }
+        }
+
+        log::debug!(
+            "Executed {:?} on window {:?}: {:?} -> {:?}",
+            action,
+            context.window,
+            from,
+            to
+        );
+
+        Ok(ActionOutcome::Moved {
+            window: context.window,
+            from,
+            to,
+        })
+    }
+
+    /// Returns the windows on a monitor in tiling order. Windows that have not been tiled
+    /// before go last, topmost first.
+    fn windows_to_tile(&mut self, monitor: MonitorId) -> Result<Vec<WindowId>> {
+        let bounds = self
+            .backend
+            .monitors()?
+            .iter()
+            .find(|info| info.id == monitor)
+            .map(|info| info.bounds)
+            .ok_or(Error::NoSuchMonitor(monitor))?;
+
+        let mut on_monitor = Vec::new();
+        for window in self.backend.windows()? {
+            if self.backend.window_monitor(window)? != monitor {
+                continue;
+            }
+
+            // Rules can keep windows out of tiling, as can the ignore list.
+            if self.window_behaviour(window)? == Some(WindowBehaviour::Float) {
+                continue;
+            }
+
+            let rect = self.backend.window_rect(window)?;
+            if let Some(reason) = self.ignore_reason(window, &rect, &bounds)? {
+                log::debug!("Not tiling window {:?} because {}", window, reason);
+                continue;
+            }
+
+            on_monitor.push(window);
+        }
+
+        for &window in &on_monitor {
+            if !self.tiling_order.contains(&window) {
+                self.tiling_order.push(window);
+            }
+        }
+
+        Ok(self
+            .tiling_order
+            .iter()
+            .copied()
+            .filter(|window| on_monitor.contains(window))
+            .collect())
+    }
+
+    /// Arranges every window on a monitor using the configured tiling layout. A window that
+    /// cannot be moved does not stop the others from being arranged.
+    pub fn tile_monitor(&mut self, monitor: MonitorId) -> Result<ActionOutcome> {
+        self.prune_history();
+
+        let work_area = self.backend.monitor_work_area(monitor)?;
+        let gaps = self.layout.resolve(&work_area);
+        let area = gaps.placement_area(&work_area);
+        let windows = self.windows_to_tile(monitor)?;
+        let placements = self.tiling.layout.build().arrange(&area, &windows);
+        let mut moved = Vec::new();
+
+        for (window, rect) in placements {
+            let from = self.backend.window_rect(window)?;
+            let to = rect.apply_inner_gaps(&area, &gaps);
+            if from == to {
+                continue;
+            }
+
+            if let Err(error) = self.backend.set_window_rect(window, to) {
+                log::error!("Unable to tile window {:?}: {}", window, error);
+                continue;
+            }
+
+            self.history.record(window, from);
+            moved.push(window);
+        }
+
+        log::debug!(
+            "Tiled {} windows on monitor {:?} using {:?}, moving {}",
+            windows.len(),
+            monitor,
+            self.tiling.layout,
+            moved.len()
+        );
+
+        Ok(ActionOutcome::Tiled { monitor, moved })
+    }
+
+    /// Arranges the windows on every monitor.
+    pub fn tile_all(&mut self) -> Result<Vec<ActionOutcome>> {
+        self.backend
+            .monitors()?
+            .iter()
+            .map(|monitor| self.tile_monitor(monitor.id))
+            .collect()
+    }
+
+    /// Executes actions that do not apply to any one window. Returns `None` for other
+    /// actions.
+    fn execute_on_desktop(&mut self, action: &Action) -> Option<Result<ActionOutcome>> {
+        match action {
+            Action::SaveLayout { name } => Some(self.save_layout(name)),
+            Action::RestoreLayout { name } => Some(self.restore_layout(name)),
+            Action::SwitchWorkspace(workspace) => {
+                Some(self.switch_workspace_on_focused_monitor(*workspace))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns every window that actions may move, along with what it is, where it is and
+    /// the monitor it is on.
+    fn movable_windows(
+        &self,
+        monitors: &[MonitorInfo],
+    ) -> Result<Vec<(WindowId, WindowInfo, Rect, MonitorInfo)>> {
+        let mut windows = Vec::new();
+
+        for window in self.backend.windows()? {
+            let monitor_id = self.backend.window_monitor(window)?;
+            let monitor = *monitors
+                .iter()
+                .find(|monitor| monitor.id == monitor_id)
+                .ok_or(Error::NoSuchMonitor(monitor_id))?;
+            let rect = self.backend.window_rect(window)?;
+
+            if let Some(reason) = self.ignore_reason(window, &rect, &monitor.bounds)? {
+                log::debug!("Leaving out window {:?} because {}", window, reason);
+                continue;
+            }
+
+            windows.push((window, self.backend.window_info(window)?, rect, monitor));
+        }
+
+        Ok(windows)
+    }
+
+    /// Saves where every window is under a name, replacing any layout already saved under
+    /// that name.
+    pub fn save_layout(&mut self, name: &str) -> Result<ActionOutcome> {
+        let monitors = self.backend.monitors()?;
+        let saved: Vec<SavedWindow> = self
+            .movable_windows(&monitors)?
+            .iter()
+            .map(|(_, info, rect, monitor)| SavedWindow::capture(info, *rect, monitor.work_area))
+            .collect();
+        let count = saved.len();
+
+        let mut snapshots =
+            snapshot::read_snapshots(&self.snapshot_path).map_err(Error::Snapshot)?;
+        snapshots.insert(name.to_owned(), saved);
+        snapshot::write_snapshots(&self.snapshot_path, &snapshots).map_err(Error::Snapshot)?;
+
+        log::info!("Saved {} windows as layout {:?}", count, name);
+        Ok(ActionOutcome::LayoutSaved {
+            name: name.to_owned(),
+            windows: count,
+        })
+    }
+
+    /// Moves windows back to where a saved layout had them. Saved windows that are not open
+    /// are passed over, as are open windows that are not in the layout.
+    pub fn restore_layout(&mut self, name: &str) -> Result<ActionOutcome> {
+        self.prune_history();
+
+        let snapshots = snapshot::read_snapshots(&self.snapshot_path).map_err(Error::Snapshot)?;
+        let saved = match snapshots.get(name) {
+            Some(saved) => saved,
+            None => {
+                log::info!("There is no saved layout named {:?}", name);
+                return Ok(ActionOutcome::Skipped(SkipReason::NoSuchLayout));
+            }
+        };
+
+        let monitors = self.backend.monitors()?;
+        let windows = self.movable_windows(&monitors)?;
+        let candidates: Vec<(WindowId, WindowInfo)> = windows
+            .iter()
+            .map(|(window, info, _, _)| (*window, info.clone()))
+            .collect();
+        let mut moved = Vec::new();
+
+        for (saved_index, window) in snapshot::match_windows(saved, &candidates) {
+            let to = match snapshot::restore_rect(&saved[saved_index], &monitors) {
+                Some(to) => to,
+                None => continue,
+            };
+            // Every candidate came from `windows`, so the window is always found.
+            let from = windows
+                .iter()
+                .find(|(candidate, ..)| *candidate == window)
+                .map(|(_, _, rect, _)| *rect)
+                .unwrap();
+
+            if from == to {
+                continue;
+            }
+
+            if let Err(error) = self.backend.set_window_rect(window, to) {
+                log::error!("Unable to restore window {:?}: {}", window, error);
+                continue;
+            }
+
+            self.history.record(window, from);
+            moved.push(window);
+        }
+
+        log::info!("Restored layout {:?}, moving {} windows", name, moved.len());
+        Ok(ActionOutcome::LayoutRestored {
+            name: name.to_owned(),
+            moved,
+        })
+    }
+
+    pub fn workspaces(&self) -> &Workspaces {
+        &self.workspaces
+    }
+
+    /// Brings the workspaces up to date with the windows that are currently visible.
+    fn sync_workspaces(&mut self) -> Result<()> {
+        let monitors = self.backend.monitors()?;
+        for (window, _, _, monitor) in self.movable_windows(&monitors)? {
+            self.workspaces.observe(window, monitor.id);
+        }
+
+        Ok(())
+    }
+
+    /// Hides and shows windows to carry out a workspace change. A window that cannot be
+    /// hidden or shown does not stop the others.
+    fn apply_transition(&mut self, transition: &Transition) -> (Vec<WindowId>, Vec<WindowId>) {
+        let mut apply = |windows: &[WindowId], visible: bool| {
+            windows
+                .iter()
+                .copied()
+                .filter(
+                    |&window| match self.backend.set_window_visible(window, visible) {
+                        Ok(()) => true,
+                        Err(error) => {
+                            log::error!(
+                                "Unable to change the visibility of window {:?}: {}",
+                                window,
+                                error
+                            );
+                            false
+                        }
+                    },
+                )
+                .collect::<Vec<_>>()
+        };
+
+        let hidden = apply(&transition.hide, false);
+        let shown = apply(&transition.show, true);
+        (hidden, shown)
+    }
+
+    /// Shows a workspace on the monitor of the focused window, or on the primary monitor if
+    /// no window has focus.
+    fn switch_workspace_on_focused_monitor(&mut self, workspace: u32) -> Result<ActionOutcome> {
+        let monitor = match self.backend.focused_window() {
+            Some(window) => self.backend.window_monitor(window)?,
+            None => {
+                let monitors = self.backend.monitors()?;
+                monitors
+                    .iter()
+                    .find(|monitor| monitor.primary)
+                    .or_else(|| monitors.first())
+                    .ok_or(Error::NoMonitors)?
+                    .id
+            }
+        };
+
+        self.switch_workspace(monitor, workspace)
+    }
+
+    /// Shows a workspace on a monitor, hiding the windows on the workspace that was shown
+    /// before.
+    pub fn switch_workspace(
+        &mut self,
+        monitor: MonitorId,
+        workspace: u32,
+    ) -> Result<ActionOutcome> {
+        self.prune_history();
+        self.sync_workspaces()?;
+
+        if self.workspaces.active(monitor) == workspace {
+            log::debug!(
+                "Workspace {} is already shown on monitor {:?}",
+                workspace,
+                monitor
+            );
+            return Ok(ActionOutcome::Skipped(SkipReason::Unchanged));
+        }
+
+        let transition = self.workspaces.switch(monitor, workspace);
+        let (hidden, shown) = self.apply_transition(&transition);
+        Ok(ActionOutcome::WorkspaceChanged {
+            monitor,
+            workspace,
+            hidden,
+            shown,
+        })
+    }
+
+    /// Moves a window to a workspace on a monitor.
+    pub fn move_to_workspace(
+        &mut self,
+        window: WindowId,
+        monitor: MonitorId,
+        workspace: u32,
+    ) -> Result<ActionOutcome> {
+        self.sync_workspaces()?;
+
+        if self.workspaces.workspace_of(window) == Some(workspace) {
+            return Ok(ActionOutcome::Skipped(SkipReason::Unchanged));
+        }
+
+        let transition = self.workspaces.move_window(window, monitor, workspace);
+        let (hidden, shown) = self.apply_transition(&transition);
+        Ok(ActionOutcome::WorkspaceChanged {
+            monitor,
+            workspace,
+            hidden,
+            shown,
+        })
+    }
+
+    /// Shows every window that is hidden on a workspace, and forgets all workspaces. This
+    /// must be called before whimsy exits, or hidden windows would stay hidden.
+    pub fn show_all_workspaces(&mut self) {
+        let transition = Transition {
+            hide: Vec::new(),
+            show: self.workspaces.hidden(),
+        };
+
+        self.apply_transition(&transition);
+        self.workspaces.clear();
+    }
+
+    /// Focuses the nearest window in a direction from `window`.
+    pub fn focus_in_direction(
+        &mut self,
+        window: WindowId,
+        window_rect: &Rect,
+        direction: Direction,
+    ) -> Result<ActionOutcome> {
+        let monitors = self.backend.monitors()?;
+        let (candidates, rects): (Vec<WindowId>, Vec<Rect>) = self
+            .movable_windows(&monitors)?
+            .into_iter()
+            .filter(|(candidate, ..)| *candidate != window)
+            .map(|(candidate, _, rect, _)| (candidate, rect))
+            .unzip();
+
+        let target = match select_neighbour(window_rect, &rects, direction) {
+            Some(index) => candidates[index],
+            None => {
+                log::debug!("There is no window {:?} of window {:?}", direction, window);
+                return Ok(ActionOutcome::Skipped(SkipReason::NoWindowInDirection));
+            }
+        };
+
+        self.backend.focus_window(target)?;
+        Ok(ActionOutcome::Focused {
+            from: window,
+            to: target,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{VirtualDesktop, WindowBackend};
+    use crate::config::{Metric, TilingLayout};
+
+    const WORK_AREA: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+
+    const SECOND_WORK_AREA: Rect = Rect {
+        left: 1920,
+        top: 0,
+        right: 3840,
+        bottom: 1040,
+    };
+
+    /// An executor over two side-by-side monitors, with one window focused on the first.
+    fn executor() -> (ActionExecutor<VirtualDesktop>, WindowId) {
+        let mut desktop = VirtualDesktop::new();
+        desktop.add_monitor(Rect::xyxy(0, 0, 1920, 1080), WORK_AREA);
+        desktop.add_monitor(Rect::xyxy(1920, 0, 3840, 1080), SECOND_WORK_AREA);
+        let window = desktop.add_window(Rect::xyxy(100, 100, 500, 400));
+
+        (ActionExecutor::new(desktop), window)
+    }
+
+    fn push(direction: Direction, fraction: Fraction) -> Action {
+        Action::Push {
+            direction,
+            fraction,
+        }
+    }
+
+    fn rect_of(executor: &ActionExecutor<VirtualDesktop>, window: WindowId) -> Rect {
+        executor.backend().window_rect(window).unwrap()
+    }
+
+    #[test]
+    fn push_takes_a_fraction_of_the_work_area() {
+        let (mut executor, window) = executor();
+
+        let outcome = executor
+            .execute(&push(Direction::Left, Fraction::Single(2.0)))
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            ActionOutcome::Moved {
+                window,
+                from: Rect::xyxy(100, 100, 500, 400),
+                to: Rect::xyxy(0, 0, 960, 1040),
+            }
+        );
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 960, 1040));
+
+        executor
+            .execute(&push(Direction::DownRight, Fraction::Single(4.0)))
+            .unwrap();
+        assert_eq!(
+            rect_of(&executor, window),
+            Rect::xyxy(1440, 780, 1920, 1040)
+        );
+    }
+
+    #[test]
+    fn push_to_the_same_place_is_skipped() {
+        let (mut executor, _) = executor();
+        let action = push(Direction::Up, Fraction::Single(2.0));
+
+        executor.execute(&action).unwrap();
+        assert_eq!(
+            executor.execute(&action).unwrap(),
+            ActionOutcome::Skipped(SkipReason::Unchanged)
+        );
+    }
+
+    #[test]
+    fn nudge_moves_by_a_distance() {
+        let (mut executor, window) = executor();
+
+        executor
+            .execute(&Action::Nudge {
+                direction: Direction::Right,
+                distance: Metric::Absolute(10.0),
+            })
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(110, 100, 510, 400));
+
+        // Percentages are of the window's own size.
+        executor
+            .execute(&Action::Nudge {
+                direction: Direction::UpLeft,
+                distance: Metric::Percent(0.5),
+            })
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(-90, -50, 310, 250));
+    }
+
+    #[test]
+    fn resize_moves_edges_by_a_distance() {
+        let (mut executor, window) = executor();
+
+        // Percentages are of the window's size along each edge's axis.
+        executor
+            .execute(&Action::Resize {
+                edge: Direction::DownRight,
+                delta: Metric::Percent(0.1),
+                clamp: true,
+            })
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(100, 100, 540, 430));
+
+        // Clamped edges stop at the work area, and windows keep a minimum size.
+        executor
+            .execute(&Action::Resize {
+                edge: Direction::Left,
+                delta: Metric::Absolute(500.0),
+                clamp: true,
+            })
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 100, 540, 430));
+
+        executor
+            .execute(&Action::Resize {
+                edge: Direction::Up,
+                delta: Metric::Absolute(-1000.0),
+                clamp: true,
+            })
+            .unwrap();
+        assert_eq!(
+            rect_of(&executor, window),
+            Rect::xyxy(0, 430 - MINIMUM_WINDOW_SIZE, 540, 430)
+        );
+
+        // Unclamped edges can leave the work area.
+        executor
+            .execute(&Action::Resize {
+                edge: Direction::Left,
+                delta: Metric::Absolute(100.0),
+                clamp: false,
+            })
+            .unwrap();
+        assert_eq!(
+            rect_of(&executor, window),
+            Rect::xyxy(-100, 430 - MINIMUM_WINDOW_SIZE, 540, 430)
+        );
+    }
+
+    #[test]
+    fn repeated_push_cycles_through_fractions() {
+        let (mut executor, window) = executor();
+        let action = push(Direction::Left, Fraction::Cycle(vec![2.0, 3.0]));
+
+        executor.execute(&action).unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 960, 1040));
+        executor.execute(&action).unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 640, 1040));
+        executor.execute(&action).unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 960, 1040));
+
+        // Moving the window some other way starts the cycle over.
+        executor
+            .backend_mut()
+            .set_window_rect(window, Rect::xyxy(100, 100, 500, 400))
+            .unwrap();
+        executor.execute(&action).unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 960, 1040));
+    }
+
+    #[test]
+    fn cycling_push_passes_over_steps_that_would_not_move() {
+        let (mut executor, window) = executor();
+        executor
+            .backend_mut()
+            .set_window_rect(window, Rect::xyxy(0, 0, 960, 1040))
+            .unwrap();
+
+        executor
+            .execute(&push(Direction::Left, Fraction::Cycle(vec![2.0, 3.0])))
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 640, 1040));
+    }
+
+    #[test]
+    fn undo_redo_and_restore() {
+        let (mut executor, window) = executor();
+        let original = rect_of(&executor, window);
+        let left = Rect::xyxy(0, 0, 960, 1040);
+        let right = Rect::xyxy(960, 0, 1920, 1040);
+
+        executor
+            .execute(&push(Direction::Left, Fraction::Single(2.0)))
+            .unwrap();
+        executor
+            .execute(&push(Direction::Right, Fraction::Single(2.0)))
+            .unwrap();
+
+        executor.execute(&Action::Undo).unwrap();
+        assert_eq!(rect_of(&executor, window), left);
+        executor.execute(&Action::Undo).unwrap();
+        assert_eq!(rect_of(&executor, window), original);
+        assert_eq!(
+            executor.execute(&Action::Undo).unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoHistory)
+        );
+
+        executor.execute(&Action::Redo).unwrap();
+        assert_eq!(rect_of(&executor, window), left);
+        executor.execute(&Action::Redo).unwrap();
+        assert_eq!(rect_of(&executor, window), right);
+        assert_eq!(
+            executor.execute(&Action::Redo).unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoHistory)
+        );
+
+        executor.execute(&Action::Restore).unwrap();
+        assert_eq!(rect_of(&executor, window), original);
+    }
+
+    #[test]
+    fn destroyed_windows_lose_their_history() {
+        let (mut executor, window) = executor();
+        executor
+            .execute(&push(Direction::Left, Fraction::Single(2.0)))
+            .unwrap();
+
+        // Handles are reused, so whatever has this handle next must not undo to the old
+        // window's place.
+        executor.window_destroyed(window).unwrap();
+        assert_eq!(
+            executor.execute(&Action::Undo).unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoHistory)
+        );
+        assert_eq!(
+            executor.execute(&Action::Restore).unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoHistory)
+        );
+    }
+
+    #[test]
+    fn new_moves_discard_redo() {
+        let (mut executor, window) = executor();
+
+        executor
+            .execute(&push(Direction::Left, Fraction::Single(2.0)))
+            .unwrap();
+        executor.execute(&Action::Undo).unwrap();
+        executor.execute(&Action::Maximize).unwrap();
+
+        assert_eq!(
+            executor.execute(&Action::Redo).unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoHistory)
+        );
+        assert_eq!(rect_of(&executor, window), WORK_AREA);
+    }
+
+    #[test]
+    fn move_to_monitor_keeps_the_relative_position() {
+        let (mut executor, window) = executor();
+        executor
+            .execute(&push(Direction::Left, Fraction::Single(2.0)))
+            .unwrap();
+
+        executor
+            .execute(&Action::MoveToMonitor {
+                target: MonitorTarget::Next,
+            })
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(1920, 0, 2880, 1040));
+
+        executor
+            .execute(&Action::MoveToMonitor {
+                target: MonitorTarget::Direction(Direction::Left),
+            })
+            .unwrap();
+        assert_eq!(rect_of(&executor, window), Rect::xyxy(0, 0, 960, 1040));
+
+        assert_eq!(
+            executor
+                .execute(&Action::MoveToMonitor {
+                    target: MonitorTarget::Direction(Direction::Up),
+                })
+                .unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoTargetMonitor)
+        );
+        assert_eq!(
+            executor
+                .execute(&Action::MoveToMonitor {
+                    target: MonitorTarget::Index(2),
+                })
+                .unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoTargetMonitor)
+        );
+    }
+
+    #[test]
+    fn tiling_fills_the_space_left_by_hidden_windows() {
+        let (mut executor, first) = executor();
+        let second = executor
+            .backend_mut()
+            .add_window(Rect::xyxy(200, 200, 600, 500));
+        let third = executor
+            .backend_mut()
+            .add_window(Rect::xyxy(300, 300, 700, 600));
+        executor.apply_config(&Config {
+            tiling: TilingConfig {
+                enabled: true,
+                layout: TilingLayout::Columns,
+            },
+            ..Config::default()
+        });
+
+        // Windows are tiled topmost first.
+        executor.tile_all().unwrap();
+        assert_eq!(rect_of(&executor, third), Rect::xyxy(0, 0, 640, 1040));
+        assert_eq!(rect_of(&executor, second), Rect::xyxy(640, 0, 1280, 1040));
+        assert_eq!(rect_of(&executor, first), Rect::xyxy(1280, 0, 1920, 1040));
+
+        executor
+            .backend_mut()
+            .set_window_visible(second, false)
+            .unwrap();
+        executor.window_hidden(second).unwrap();
+        assert_eq!(rect_of(&executor, third), Rect::xyxy(0, 0, 960, 1040));
+        assert_eq!(rect_of(&executor, first), Rect::xyxy(960, 0, 1920, 1040));
+
+        // Closed windows leave a gap too, and windows that were never tiled are ignored.
+        executor.backend_mut().remove_window(first);
+        executor.window_destroyed(first).unwrap();
+        assert_eq!(rect_of(&executor, third), WORK_AREA);
+        assert_eq!(executor.window_hidden(WindowId(1000)).unwrap(), vec![]);
+
+        // A window that comes back takes its old place.
+        executor
+            .backend_mut()
+            .set_window_visible(second, true)
+            .unwrap();
+        executor.window_shown(second).unwrap();
+        assert_eq!(rect_of(&executor, third), Rect::xyxy(0, 0, 960, 1040));
+        assert_eq!(rect_of(&executor, second), Rect::xyxy(960, 0, 1920, 1040));
+    }
+
+    #[test]
+    fn workspaces_hide_windows_until_shown_again() {
+        let (mut executor, first) = executor();
+        let second = executor
+            .backend_mut()
+            .add_window(Rect::xyxy(200, 200, 600, 500));
+
+        executor.execute(&Action::MoveToWorkspace(2)).unwrap();
+        assert!(!executor.backend().window(second).unwrap().visible);
+
+        executor.execute(&Action::SwitchWorkspace(2)).unwrap();
+        assert!(executor.backend().window(second).unwrap().visible);
+        assert!(!executor.backend().window(first).unwrap().visible);
+
+        executor.show_all_workspaces();
+        assert!(executor.backend().window(first).unwrap().visible);
+        assert!(executor.backend().window(second).unwrap().visible);
+        assert!(executor.workspaces().hidden().is_empty());
+    }
+
+    #[test]
+    fn neighbours_must_lie_in_the_direction() {
+        let from = Rect::xyxy(400, 400, 600, 600);
+        let candidates = [
+            Rect::xyxy(0, 400, 200, 600),
+            Rect::xyxy(800, 400, 1000, 600),
+            Rect::xyxy(400, 0, 600, 200),
+        ];
+
+        assert_eq!(
+            select_neighbour(&from, &candidates, Direction::Left),
+            Some(0)
+        );
+        assert_eq!(
+            select_neighbour(&from, &candidates, Direction::Right),
+            Some(1)
+        );
+        assert_eq!(select_neighbour(&from, &candidates, Direction::Up), Some(2));
+        assert_eq!(select_neighbour(&from, &candidates, Direction::Down), None);
+        assert_eq!(select_neighbour(&from, &[], Direction::Left), None);
+    }
+
+    #[test]
+    fn neighbours_that_overlap_are_preferred() {
+        let from = Rect::xyxy(400, 400, 600, 600);
+        let candidates = [
+            // Closer, but entirely above the window.
+            Rect::xyxy(650, 200, 750, 390),
+            // Further away, but level with the window.
+            Rect::xyxy(1200, 450, 1400, 650),
+        ];
+
+        assert_eq!(
+            select_neighbour(&from, &candidates, Direction::Right),
+            Some(1)
+        );
+        // Without the level window, the one above is the nearest to the right.
+        assert_eq!(
+            select_neighbour(&from, &candidates[..1], Direction::Right),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn nearer_neighbours_are_preferred() {
+        let from = Rect::xyxy(400, 400, 600, 600);
+        let candidates = [
+            Rect::xyxy(1000, 400, 1200, 600),
+            Rect::xyxy(700, 400, 900, 600),
+            // As near along the direction, but off to the side.
+            Rect::xyxy(700, 500, 900, 700),
+        ];
+
+        assert_eq!(
+            select_neighbour(&from, &candidates, Direction::Right),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn diagonal_neighbours_must_lie_in_both_directions() {
+        let from = Rect::xyxy(400, 400, 600, 600);
+        let candidates = [
+            // Straight up, so not up and to the right.
+            Rect::xyxy(400, 0, 600, 200),
+            // Straight to the right.
+            Rect::xyxy(800, 400, 1000, 600),
+            Rect::xyxy(900, 0, 1100, 200),
+            Rect::xyxy(700, 100, 900, 300),
+        ];
+
+        assert_eq!(
+            select_neighbour(&from, &candidates, Direction::UpRight),
+            Some(3)
+        );
+        assert_eq!(
+            select_neighbour(&from, &candidates[..2], Direction::UpRight),
+            None
+        );
+        assert_eq!(
+            select_neighbour(&from, &candidates, Direction::DownLeft),
+            None
+        );
+    }
+
+    #[test]
+    fn focus_moves_to_the_neighbouring_window() {
+        let (mut executor, left) = executor();
+        executor
+            .backend_mut()
+            .set_window_rect(left, Rect::xyxy(0, 0, 960, 1040))
+            .unwrap();
+        let right = executor
+            .backend_mut()
+            .add_window(Rect::xyxy(960, 0, 1920, 1040));
+
+        assert_eq!(
+            executor
+                .execute(&Action::Focus {
+                    direction: Direction::Left
+                })
+                .unwrap(),
+            ActionOutcome::Focused {
+                from: right,
+                to: left
+            }
+        );
+        assert_eq!(executor.backend().focused_window(), Some(left));
+        assert_eq!(
+            executor
+                .execute(&Action::Focus {
+                    direction: Direction::Left
+                })
+                .unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoWindowInDirection)
+        );
+    }
+
+    #[test]
+    fn actions_need_a_focused_window() {
+        let (mut executor, _) = executor();
+        executor.backend_mut().clear_focus();
+
+        assert_eq!(
+            executor.execute(&Action::Maximize).unwrap(),
+            ActionOutcome::Skipped(SkipReason::NoFocusedWindow)
+        );
+    }
+}
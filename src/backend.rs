@@ -0,0 +1,106 @@
+//! Platform abstraction over the windows and monitors that whimsy manipulates.
+//!
+//! Action logic only talks to the [`WindowBackend`] and [`MonitorBackend`] traits. The
+//! Win32 implementation drives the real desktop; [`VirtualDesktop`] keeps everything in
+//! memory so that actions can be exercised on any OS.
+use std::path::PathBuf;
+
+use crate::error::Result;
+use crate::window::Rect;
+
+mod virtual_desktop;
+#[cfg(windows)]
+mod win32;
+
+pub use virtual_desktop::{VirtualDesktop, VirtualMonitor, VirtualWindow};
+#[cfg(windows)]
+pub use win32::Win32Backend;
+
+/// Opaque identifier for a top-level window. On Win32, this is the window handle.
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct WindowId(pub isize);
+
+/// Opaque identifier for a monitor. On Win32, this is the monitor handle.
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct MonitorId(pub isize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MonitorInfo {
+    pub id: MonitorId,
+    /// The full bounds of the monitor.
+    pub bounds: Rect,
+    /// The part of the monitor available to windows.
+    pub work_area: Rect,
+    pub primary: bool,
+}
+
+/// What a window is, as opposed to where it is. Used to pick out windows for rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowInfo {
+    /// The name of the window's class. On Win32, this is the name it was registered with.
+    pub class: String,
+    pub title: String,
+    pub process_id: u32,
+    /// The path of the executable that owns the window. This is `None` if the process
+    /// cannot be inspected, e.g. because it is running elevated.
+    pub executable: Option<PathBuf>,
+    /// Whether this is a tool window, such as a floating toolbar or palette.
+    pub tool_window: bool,
+    /// Whether the window lacks a sizing border, so that the user cannot resize it.
+    pub fixed_size: bool,
+    /// Whether the window has no title bar, as full-screen windows do.
+    pub borderless: bool,
+}
+
+/// Sorts monitors by position: left to right, and top to bottom for monitors that share a
+/// left edge. This gives monitors an order that does not depend on how the OS enumerates
+/// them.
+pub fn sort_monitors(monitors: &mut [MonitorInfo]) {
+    monitors.sort_by_key(|monitor| (monitor.bounds.left, monitor.bounds.top));
+}
+
+pub trait WindowBackend {
+    /// Returns the window that currently has keyboard focus, if there is one.
+    fn focused_window(&self) -> Option<WindowId>;
+
+    /// Returns the windows that the user can see and arrange, in z-order, topmost first.
+    fn windows(&self) -> Result<Vec<WindowId>>;
+
+    /// Whether the window still exists. Window IDs may be reused once a window has been
+    /// destroyed.
+    fn window_exists(&self, window: WindowId) -> bool;
+
+    fn window_info(&self, window: WindowId) -> Result<WindowInfo>;
+
+    fn window_rect(&self, window: WindowId) -> Result<Rect>;
+
+    fn set_window_rect(&mut self, window: WindowId, rect: Rect) -> Result<()>;
+
+    /// Gives a window keyboard focus and brings it to the front.
+    fn focus_window(&mut self, window: WindowId) -> Result<()>;
+
+    /// Hides or shows a window. Hidden windows are left out of [`WindowBackend::windows`].
+    fn set_window_visible(&mut self, window: WindowId, visible: bool) -> Result<()>;
+
+    /// Returns the monitor that the window is on. If the window spans several monitors,
+    /// this is the one it overlaps the most; if it is on none, the nearest one.
+    fn window_monitor(&self, window: WindowId) -> Result<MonitorId>;
+}
+
+pub trait MonitorBackend {
+    /// Returns the area of the monitor that is not covered by the taskbar or other
+    /// docked toolbars.
+    fn monitor_work_area(&self, monitor: MonitorId) -> Result<Rect>;
+
+    /// Returns every monitor, ordered by [`sort_monitors`].
+    fn monitors(&self) -> Result<Vec<MonitorInfo>>;
+}
+
+/// Convenience trait for anything that can act as a complete desktop.
+pub trait Backend: WindowBackend + MonitorBackend {}
+
+impl<T: WindowBackend + MonitorBackend> Backend for T {}
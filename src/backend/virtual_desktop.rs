@@ -0,0 +1,245 @@
+use super::{MonitorBackend, MonitorId, MonitorInfo, WindowBackend, WindowId, WindowInfo};
+use crate::error::{Error, Result};
+use crate::window::Rect;
+
+#[derive(Debug, Clone)]
+pub struct VirtualMonitor {
+    pub id: MonitorId,
+    /// The full bounds of the monitor.
+    pub bounds: Rect,
+    /// The part of the monitor available to windows.
+    pub work_area: Rect,
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VirtualWindow {
+    pub id: WindowId,
+    pub rect: Rect,
+    pub info: WindowInfo,
+    pub visible: bool,
+}
+
+/// An in-memory desktop holding windows, monitors, focus and z-order. Nothing here touches
+/// the OS, which makes it suitable for driving actions deterministically in tests.
+#[derive(Debug, Default)]
+pub struct VirtualDesktop {
+    monitors: Vec<VirtualMonitor>,
+    /// Windows in z-order, topmost first.
+    windows: Vec<VirtualWindow>,
+    focused: Option<WindowId>,
+    id_increment: isize,
+}
+
+impl VirtualDesktop {
+    pub fn new() -> VirtualDesktop {
+        VirtualDesktop::default()
+    }
+
+    fn next_id(&mut self) -> isize {
+        self.id_increment += 1;
+        self.id_increment
+    }
+
+    /// Adds a monitor. The first monitor added becomes the primary monitor.
+    pub fn add_monitor(&mut self, bounds: Rect, work_area: Rect) -> MonitorId {
+        let id = MonitorId(self.next_id());
+        let primary = self.monitors.is_empty();
+        self.monitors.push(VirtualMonitor {
+            id,
+            bounds,
+            work_area,
+            primary,
+        });
+        id
+    }
+
+    pub fn set_primary_monitor(&mut self, id: MonitorId) -> Result<()> {
+        if self.monitor(id).is_none() {
+            return Err(Error::NoSuchMonitor(id));
+        }
+
+        for monitor in &mut self.monitors {
+            monitor.primary = monitor.id == id;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a window on top of every other window and gives it focus, mirroring what
+    /// happens when a window is opened on a real desktop.
+    pub fn add_window(&mut self, rect: Rect) -> WindowId {
+        let id = WindowId(self.next_id());
+        self.windows.insert(
+            0,
+            VirtualWindow {
+                id,
+                rect,
+                info: WindowInfo::default(),
+                visible: true,
+            },
+        );
+        self.focused = Some(id);
+        id
+    }
+
+    /// Sets the class, title and process information that a window reports.
+    pub fn set_window_info(&mut self, id: WindowId, info: WindowInfo) -> Result<()> {
+        let index = self.window_index(id)?;
+        self.windows[index].info = info;
+        Ok(())
+    }
+
+    /// Removes a window. If it was focused, focus passes to the next window in z-order.
+    pub fn remove_window(&mut self, id: WindowId) -> bool {
+        let index = match self.windows.iter().position(|window| window.id == id) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        self.windows.remove(index);
+        if self.focused == Some(id) {
+            self.focused = self.windows.first().map(|window| window.id);
+        }
+
+        true
+    }
+
+    /// Focuses a window and raises it to the top of the z-order.
+    pub fn focus(&mut self, id: WindowId) -> Result<()> {
+        let index = self.window_index(id)?;
+        let window = self.windows.remove(index);
+        self.windows.insert(0, window);
+        self.focused = Some(id);
+        Ok(())
+    }
+
+    /// Clears focus, as happens when the desktop itself is clicked.
+    pub fn clear_focus(&mut self) {
+        self.focused = None;
+    }
+
+    /// Returns window IDs in z-order, topmost first.
+    pub fn z_order(&self) -> Vec<WindowId> {
+        self.windows.iter().map(|window| window.id).collect()
+    }
+
+    pub fn window(&self, id: WindowId) -> Option<&VirtualWindow> {
+        self.windows.iter().find(|window| window.id == id)
+    }
+
+    pub fn monitor(&self, id: MonitorId) -> Option<&VirtualMonitor> {
+        self.monitors.iter().find(|monitor| monitor.id == id)
+    }
+
+    pub fn monitors(&self) -> &[VirtualMonitor] {
+        &self.monitors
+    }
+
+    fn window_index(&self, id: WindowId) -> Result<usize> {
+        self.windows
+            .iter()
+            .position(|window| window.id == id)
+            .ok_or(Error::NoSuchWindow(id))
+    }
+}
+
+impl WindowBackend for VirtualDesktop {
+    fn focused_window(&self) -> Option<WindowId> {
+        self.focused
+    }
+
+    fn windows(&self) -> Result<Vec<WindowId>> {
+        Ok(self
+            .windows
+            .iter()
+            .filter(|window| window.visible)
+            .map(|window| window.id)
+            .collect())
+    }
+
+    fn window_exists(&self, window: WindowId) -> bool {
+        self.window(window).is_some()
+    }
+
+    fn window_info(&self, window: WindowId) -> Result<WindowInfo> {
+        let index = self.window_index(window)?;
+        Ok(self.windows[index].info.clone())
+    }
+
+    fn window_rect(&self, window: WindowId) -> Result<Rect> {
+        let index = self.window_index(window)?;
+        Ok(self.windows[index].rect)
+    }
+
+    fn set_window_rect(&mut self, window: WindowId, rect: Rect) -> Result<()> {
+        let index = self.window_index(window)?;
+        self.windows[index].rect = rect;
+        Ok(())
+    }
+
+    fn focus_window(&mut self, window: WindowId) -> Result<()> {
+        self.focus(window)
+    }
+
+    fn set_window_visible(&mut self, window: WindowId, visible: bool) -> Result<()> {
+        let index = self.window_index(window)?;
+        self.windows[index].visible = visible;
+        Ok(())
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Result<MonitorId> {
+        let rect = self.window_rect(window)?;
+
+        let overlapping = self
+            .monitors
+            .iter()
+            .filter_map(|monitor| {
+                monitor
+                    .bounds
+                    .intersection(&rect)
+                    .map(|overlap| (monitor, overlap.area()))
+            })
+            .max_by_key(|&(_, area)| area);
+
+        if let Some((monitor, _)) = overlapping {
+            return Ok(monitor.id);
+        }
+
+        // Like MONITOR_DEFAULTTONEAREST, fall back to the monitor closest to the window.
+        let (x, y) = rect.center();
+        self.monitors
+            .iter()
+            .min_by_key(|monitor| {
+                let (mx, my) = monitor.bounds.center();
+                let (dx, dy) = ((mx - x) as i64, (my - y) as i64);
+                dx * dx + dy * dy
+            })
+            .map(|monitor| monitor.id)
+            .ok_or(Error::NoMonitors)
+    }
+}
+
+impl MonitorBackend for VirtualDesktop {
+    fn monitor_work_area(&self, monitor: MonitorId) -> Result<Rect> {
+        self.monitor(monitor)
+            .map(|monitor| monitor.work_area)
+            .ok_or(Error::NoSuchMonitor(monitor))
+    }
+
+    fn monitors(&self) -> Result<Vec<MonitorInfo>> {
+        let mut monitors: Vec<MonitorInfo> = self
+            .monitors
+            .iter()
+            .map(|monitor| MonitorInfo {
+                id: monitor.id,
+                bounds: monitor.bounds,
+                work_area: monitor.work_area,
+                primary: monitor.primary,
+            })
+            .collect();
+
+        super::sort_monitors(&mut monitors);
+        Ok(monitors)
+    }
+}
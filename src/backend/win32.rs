@@ -0,0 +1,72 @@
+use super::{MonitorBackend, MonitorId, MonitorInfo, WindowBackend, WindowId, WindowInfo};
+use crate::error::Result;
+use crate::window::{self, Monitor, Rect, Window};
+
+/// Backend that operates on the live Win32 desktop.
+#[derive(Debug, Default)]
+pub struct Win32Backend;
+
+impl WindowBackend for Win32Backend {
+    fn focused_window(&self) -> Option<WindowId> {
+        window::get_focused_window().map(|window| window.id())
+    }
+
+    fn windows(&self) -> Result<Vec<WindowId>> {
+        Ok(window::enumerate_windows()?
+            .iter()
+            .filter(|window| window.is_arrangeable())
+            .map(Window::id)
+            .collect())
+    }
+
+    fn window_exists(&self, window: WindowId) -> bool {
+        Window::from_id(window).exists()
+    }
+
+    fn window_info(&self, window: WindowId) -> Result<WindowInfo> {
+        Window::from_id(window).get_info()
+    }
+
+    fn window_rect(&self, window: WindowId) -> Result<Rect> {
+        Window::from_id(window).get_rect()
+    }
+
+    fn set_window_rect(&mut self, window: WindowId, rect: Rect) -> Result<()> {
+        Window::from_id(window).set_rect(rect)
+    }
+
+    fn focus_window(&mut self, window: WindowId) -> Result<()> {
+        Window::from_id(window).activate()
+    }
+
+    fn set_window_visible(&mut self, window: WindowId, visible: bool) -> Result<()> {
+        let mut window = Window::from_id(window);
+        if visible {
+            window.show();
+        } else {
+            window.hide();
+        }
+
+        Ok(())
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Result<MonitorId> {
+        Ok(Window::from_id(window).get_monitor().id())
+    }
+}
+
+impl MonitorBackend for Win32Backend {
+    fn monitor_work_area(&self, monitor: MonitorId) -> Result<Rect> {
+        Monitor::from_id(monitor).get_work_area()
+    }
+
+    fn monitors(&self) -> Result<Vec<MonitorInfo>> {
+        let mut monitors = window::enumerate_monitors()?
+            .iter()
+            .map(Monitor::get_info)
+            .collect::<Result<Vec<_>>>()?;
+
+        super::sort_monitors(&mut monitors);
+        Ok(monitors)
+    }
+}
@@ -14,6 +14,38 @@ pub enum CliCommand {
         about = "Restores the whimsy configuration file to the default."
     )]
     RegenerateConfigFile,
+    #[structopt(
+        name = "check-bindings",
+        about = "Checks that every configured hotkey can be registered, and reports any that conflict."
+    )]
+    CheckBindings,
+    #[structopt(
+        name = "msg",
+        about = "Sends an action to the running whimsy, either as YAML or JSON or in short form such as `push left 2`."
+    )]
+    Message {
+        #[structopt(required = true)]
+        /// The action to execute.
+        action: Vec<String>,
+    },
+    #[structopt(
+        name = "run",
+        about = "Executes an action once on the focused window and exits, without the running whimsy. Takes the same forms as `msg`."
+    )]
+    Run {
+        #[structopt(required = true)]
+        /// The action to execute.
+        action: Vec<String>,
+    },
+    #[structopt(
+        name = "validate",
+        about = "Checks a configuration file for problems, exiting with an error code if there are any."
+    )]
+    Validate {
+        #[structopt(parse(from_os_str))]
+        /// The file to check. Defaults to the configuration file whimsy uses.
+        path: Option<PathBuf>,
+    },
 }
 
 #[derive(StructOpt, Debug)]
@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
 use std::default::Default;
 use std::path::{Path, PathBuf};
 
 use crate::keybind;
+use crate::rules::Pattern;
+use crate::window::{Gaps, Rect};
 
 lazy_static::lazy_static! {
     pub static ref DEFAULT_CONFIG_PATH: PathBuf = {
@@ -14,9 +17,37 @@ lazy_static::lazy_static! {
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigReadError {
-    #[error("could not read config file: {0}")]
-    IoError(std::io::Error),
-    #[error("could not deserialize config file contents: {0}")]
+    #[error("could not read config file {}: {error}", path.display())]
+    IoError {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    #[error("could not deserialize config file {}: {error}", path.display())]
+    DeserializeError {
+        path: PathBuf,
+        error: serde_yaml::Error,
+    },
+    #[error("config file {} includes {pattern:?}, which does not exist", path.display())]
+    MissingInclude { path: PathBuf, pattern: String },
+    #[error("config files include each other: {}", format_cycle(.0))]
+    IncludeCycle(Vec<PathBuf>),
+}
+
+fn format_cycle(files: &[PathBuf]) -> String {
+    files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ActionParseError {
+    #[error("no action was given")]
+    Empty,
+    #[error("{0:?} takes at most {1} arguments")]
+    TooManyArguments(String, usize),
+    #[error("could not parse action: {0}")]
     DeserializeError(serde_yaml::Error),
 }
 
@@ -25,70 +56,626 @@ pub enum ConfigReadError {
 pub struct ConfigDirectives {
     #[serde(rename = "live-reload")]
     pub live_reload_configuration: bool,
+    /// How long to wait for the next chord of a key sequence, in milliseconds.
+    #[serde(rename = "sequence-timeout")]
+    pub sequence_timeout_ms: u64,
 }
 
 impl Default for ConfigDirectives {
     fn default() -> Self {
         ConfigDirectives {
             live_reload_configuration: false,
+            sequence_timeout_ms: 1000,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Direction {
     Up,
     Left,
     Right,
     Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    /// Splits this direction into its vertical and horizontal parts. Diagonal directions
+    /// have both; the others have only one.
+    pub fn split(self) -> (Option<Direction>, Option<Direction>) {
+        match self {
+            Direction::Up => (Some(Direction::Up), None),
+            Direction::Down => (Some(Direction::Down), None),
+            Direction::Left => (None, Some(Direction::Left)),
+            Direction::Right => (None, Some(Direction::Right)),
+            Direction::UpLeft => (Some(Direction::Up), Some(Direction::Left)),
+            Direction::UpRight => (Some(Direction::Up), Some(Direction::Right)),
+            Direction::DownLeft => (Some(Direction::Down), Some(Direction::Left)),
+            Direction::DownRight => (Some(Direction::Down), Some(Direction::Right)),
+        }
+    }
+
+    /// The signs of the horizontal and vertical movement in this direction, in screen
+    /// coordinates (where y increases downwards).
+    pub fn components(self) -> (i32, i32) {
+        let (vertical, horizontal) = self.split();
+        let x = match horizontal {
+            Some(Direction::Left) => -1,
+            Some(_) => 1,
+            None => 0,
+        };
+        let y = match vertical {
+            Some(Direction::Up) => -1,
+            Some(_) => 1,
+            None => 0,
+        };
+
+        (x, y)
+    }
+}
+
+/// Identifies a monitor relative to the one a window is currently on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MonitorTarget {
+    /// The next monitor in position order, wrapping around.
+    Next,
+    /// The previous monitor in position order, wrapping around.
+    Previous,
+    /// The nearest monitor in a direction.
+    Direction(Direction),
+    /// A monitor by its zero-based index in position order: left to right, then top to
+    /// bottom.
+    Index(usize),
 }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Metric {
     Percent(f32),
     Absolute(f32),
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+impl Metric {
+    /// Converts this metric to pixels. Percentages are taken of `length`.
+    pub fn resolve(self, length: i32) -> i32 {
+        (match self {
+            Metric::Absolute(value) => value,
+            Metric::Percent(fraction) => length as f32 * fraction,
+        }) as i32
+    }
+}
+
+/// The fraction of the work area that a push takes up: `2` is half, `3` is a third, and
+/// so on. A list of fractions cycles through them when the same push is repeated.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Fraction {
+    Single(f32),
+    Cycle(Vec<f32>),
+}
+
+impl Fraction {
+    pub fn len(&self) -> usize {
+        match self {
+            Fraction::Single(_) => 1,
+            Fraction::Cycle(fractions) => fractions.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the fraction for a step of the cycle, wrapping around. An empty cycle takes
+    /// up the whole work area.
+    pub fn step(&self, step: usize) -> f32 {
+        match self {
+            Fraction::Single(fraction) => *fraction,
+            Fraction::Cycle(fractions) if fractions.is_empty() => 1.0,
+            Fraction::Cycle(fractions) => fractions[step % fractions.len()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Action {
     Push {
         direction: Direction,
-        fraction: f32,
+        fraction: Fraction,
     },
     Nudge {
         direction: Direction,
         distance: Metric,
     },
+    /// Places the window into a span of cells of a grid laid over the work area. Cells are
+    /// indexed from zero, starting at the top-left corner.
+    Grid {
+        columns: u32,
+        rows: u32,
+        x: u32,
+        y: u32,
+        #[serde(default = "default_grid_span")]
+        width: u32,
+        #[serde(default = "default_grid_span")]
+        height: u32,
+    },
+    /// Moves one edge of the window outward (for positive deltas) or inward (for negative
+    /// deltas). Percentages are of the window's size along the edge's axis. When `clamp` is
+    /// set, the edge stays inside the work area and the window keeps a minimum size.
+    Resize {
+        edge: Direction,
+        delta: Metric,
+        #[serde(default = "default_resize_clamp")]
+        clamp: bool,
+    },
+    /// Moves the window to another monitor, keeping its position and size relative to the
+    /// monitor's work area.
+    MoveToMonitor { target: MonitorTarget },
+    /// Centers the window in the work area. Percentages are of the work area's size.
+    Center { width: Metric, height: Metric },
+    /// Makes the window fill the work area.
+    Maximize,
+    /// Makes the window fill the work area, less a margin on every side. Percentages are of
+    /// the work area's size.
+    AlmostMaximize { margin: Metric },
+    /// Returns the window to where it was before whimsy last moved it.
+    Undo,
+    /// Reapplies a move that was undone.
+    Redo,
+    /// Returns the window to where it was before whimsy first moved it.
+    Restore,
+    /// Arranges every window on the window's monitor using the configured tiling layout.
+    Tile,
+    /// Remembers where every window is, under a name.
+    SaveLayout { name: String },
+    /// Moves windows back to where they were when a layout was saved.
+    RestoreLayout { name: String },
+    /// Shows a workspace on the focused window's monitor, hiding the windows on the
+    /// workspace that was shown before.
+    SwitchWorkspace(u32),
+    /// Moves the window to a workspace on its monitor.
+    MoveToWorkspace(u32),
+    /// Focuses the nearest window in a direction.
+    Focus { direction: Direction },
+    /// Switches to another set of bindings until the mode is exited. Entering the
+    /// `default` mode leaves every mode.
+    EnterMode { mode: String },
+    /// Returns to the mode that was active before the current one.
+    ExitMode,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+fn default_grid_span() -> u32 {
+    1
+}
+
+fn default_resize_clamp() -> bool {
+    true
+}
+
+/// How an argument of the short form of an action is turned into a field.
+#[derive(Debug, Copy, Clone)]
+enum Argument {
+    /// A field whose value is written as it would be in YAML, such as `left` or `[2, 3]`.
+    Field(&'static str),
+    /// A [`Metric`] field. Plain numbers are absolute, and numbers ending in `%` are
+    /// percentages.
+    Metric(&'static str),
+    /// A [`MonitorTarget`] field. Numbers are indices, and directions pick the nearest
+    /// monitor.
+    MonitorTarget(&'static str),
+    /// A field that takes the rest of the words, so that names can contain spaces.
+    Rest(&'static str),
+    /// The value of a newtype variant.
+    Value,
+}
+
+/// The arguments of every action that can be written in short form, in order.
+const SHORT_FORMS: &[(&str, &[Argument])] = &[
+    (
+        "push",
+        &[Argument::Field("direction"), Argument::Field("fraction")],
+    ),
+    (
+        "nudge",
+        &[Argument::Field("direction"), Argument::Metric("distance")],
+    ),
+    (
+        "grid",
+        &[
+            Argument::Field("columns"),
+            Argument::Field("rows"),
+            Argument::Field("x"),
+            Argument::Field("y"),
+            Argument::Field("width"),
+            Argument::Field("height"),
+        ],
+    ),
+    (
+        "resize",
+        &[
+            Argument::Field("edge"),
+            Argument::Metric("delta"),
+            Argument::Field("clamp"),
+        ],
+    ),
+    ("move-to-monitor", &[Argument::MonitorTarget("target")]),
+    (
+        "center",
+        &[Argument::Metric("width"), Argument::Metric("height")],
+    ),
+    ("maximize", &[]),
+    ("almost-maximize", &[Argument::Metric("margin")]),
+    ("undo", &[]),
+    ("redo", &[]),
+    ("restore", &[]),
+    ("tile", &[]),
+    ("save-layout", &[Argument::Rest("name")]),
+    ("restore-layout", &[Argument::Rest("name")]),
+    ("switch-workspace", &[Argument::Value]),
+    ("move-to-workspace", &[Argument::Value]),
+    ("focus", &[Argument::Field("direction")]),
+    ("enter-mode", &[Argument::Rest("mode")]),
+    ("exit-mode", &[]),
+];
+
+/// Parses one word of the short form as a YAML scalar, falling back to a string.
+fn short_form_value(word: &str) -> serde_yaml::Value {
+    serde_yaml::from_str(word).unwrap_or_else(|_| serde_yaml::Value::String(word.to_owned()))
+}
+
+fn short_form_argument(argument: Argument, word: &str) -> serde_yaml::Value {
+    let tagged = |tag: &str, value: serde_yaml::Value| {
+        let mut mapping = serde_yaml::Mapping::new();
+        mapping.insert(serde_yaml::Value::String(tag.to_owned()), value);
+        serde_yaml::Value::Mapping(mapping)
+    };
+
+    match argument {
+        Argument::Metric(_) => match word.strip_suffix('%').map(str::parse::<f32>) {
+            Some(Ok(percent)) => tagged("percent", (percent / 100.0).into()),
+            _ => match word.parse::<f32>() {
+                Ok(absolute) => tagged("absolute", absolute.into()),
+                Err(_) => short_form_value(word),
+            },
+        },
+        Argument::MonitorTarget(_) => match word {
+            "next" | "previous" => short_form_value(word),
+            _ => match word.parse::<usize>() {
+                Ok(index) => tagged("index", index.into()),
+                Err(_) => tagged("direction", short_form_value(word)),
+            },
+        },
+        Argument::Field(_) | Argument::Value => short_form_value(word),
+        Argument::Rest(_) => serde_yaml::Value::String(word.to_owned()),
+    }
+}
+
+/// Splits the short form of an action into words. A bracketed list is kept as one word
+/// even if it has spaces in it, so that `push left [2, 3, 1.5]` has three words.
+fn short_form_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            _ if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    words.push(&text[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        start.get_or_insert(index);
+    }
+
+    if let Some(start) = start {
+        words.push(&text[start..]);
+    }
+
+    words
+}
+
+/// Parses an action written in short form, such as `push left 2` or `switch-workspace 3`.
+/// Returns `None` if the first word is not the name of an action.
+fn parse_short_form(text: &str) -> Option<Result<Action, ActionParseError>> {
+    let mut words = short_form_words(text).into_iter();
+    let name = words.next()?;
+    let (_, arguments) = SHORT_FORMS.iter().find(|(short, _)| *short == name)?;
+    let words: Vec<&str> = words.collect();
+
+    if words.is_empty() {
+        return Some(
+            serde_yaml::from_value(serde_yaml::Value::String(name.to_owned()))
+                .map_err(ActionParseError::DeserializeError),
+        );
+    }
+
+    let takes_rest = matches!(arguments.last(), Some(Argument::Rest(_)));
+    if words.len() > arguments.len() && !takes_rest {
+        return Some(Err(ActionParseError::TooManyArguments(
+            name.to_owned(),
+            arguments.len(),
+        )));
+    }
+
+    let mut fields = serde_yaml::Mapping::new();
+    let mut value = None;
+    for (index, &argument) in arguments.iter().enumerate().take(words.len()) {
+        let field = match argument {
+            Argument::Field(field) | Argument::Metric(field) | Argument::MonitorTarget(field) => {
+                field
+            }
+            Argument::Rest(field) => {
+                fields.insert(
+                    field.into(),
+                    short_form_argument(argument, &words[index..].join(" ")),
+                );
+                break;
+            }
+            Argument::Value => {
+                value = Some(short_form_argument(argument, words[index]));
+                break;
+            }
+        };
+
+        fields.insert(field.into(), short_form_argument(argument, words[index]));
+    }
+
+    let mut action = serde_yaml::Mapping::new();
+    action.insert(
+        name.into(),
+        value.unwrap_or(serde_yaml::Value::Mapping(fields)),
+    );
+    Some(
+        serde_yaml::from_value(serde_yaml::Value::Mapping(action))
+            .map_err(ActionParseError::DeserializeError),
+    )
+}
+
+impl std::str::FromStr for Action {
+    type Err = ActionParseError;
+
+    /// Parses an action written either as it is in the configuration file, in YAML or JSON,
+    /// or in short form, where the name of the action is followed by its fields in order:
+    /// `push left 2`, `push left [2, 3]`, `nudge up 10%` or `move-to-monitor next`.
+    fn from_str(text: &str) -> Result<Action, ActionParseError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ActionParseError::Empty);
+        }
+
+        parse_short_form(text).unwrap_or_else(|| {
+            serde_yaml::from_str(text).map_err(ActionParseError::DeserializeError)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Binding {
     pub key: keybind::Key,
     pub modifiers: Vec<keybind::Modifier>,
+    /// Further chords that must be pressed, in order, after the first one, making this
+    /// binding a key sequence.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub then: Vec<keybind::Chord>,
     pub action: Action,
 }
 
+impl Binding {
+    /// Every chord that must be pressed to trigger this binding, in order.
+    pub fn sequence(&self) -> Vec<keybind::Chord> {
+        let mut sequence = vec![keybind::Chord::new(self.key, &self.modifiers)];
+        sequence.extend(
+            self.then
+                .iter()
+                .map(|chord| keybind::Chord::new(chord.key, &chord.modifiers)),
+        );
+        sequence
+    }
+}
+
+/// A named set of bindings that replaces the default bindings while it is active.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Mode {
+    pub bindings: Vec<Binding>,
+}
+
+/// Picks out windows by what they are. Every pattern that is set must match; a match with no
+/// patterns matches every window.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct WindowMatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<Pattern>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Pattern>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable: Option<Pattern>,
+}
+
+/// Special treatment for windows that match a rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowBehaviour {
+    /// The window is left out of tiling, but actions still apply to it.
+    Float,
+    /// whimsy leaves the window alone entirely.
+    Ignore,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum RuleAction {
+    Behaviour(WindowBehaviour),
+    /// An action to execute on the window when it first appears.
+    Action(Action),
+}
+
+/// Something to do to windows that match a set of patterns. When several rules match a
+/// window, the first one wins.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Rule {
+    #[serde(rename = "match")]
+    pub criteria: WindowMatch,
+    pub action: RuleAction,
+}
+
+/// Windows that actions and tiling leave alone.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct IgnoreConfig {
+    /// Ignore the taskbar, the desktop and other parts of the Windows shell.
+    pub builtin: bool,
+    pub tool_windows: bool,
+    /// Ignore windows that cannot be resized, such as most dialogs. Off by default, so that
+    /// actions still move them.
+    pub fixed_size: bool,
+    /// Ignore borderless windows that cover their whole monitor, such as full-screen games.
+    pub fullscreen: bool,
+    /// Further windows to ignore.
+    pub windows: Vec<WindowMatch>,
+}
+
+impl Default for IgnoreConfig {
+    fn default() -> Self {
+        IgnoreConfig {
+            builtin: true,
+            tool_windows: true,
+            fixed_size: false,
+            fullscreen: true,
+            windows: Vec::new(),
+        }
+    }
+}
+
+/// Spacing applied to every window placement.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LayoutConfig {
+    /// Space between windows and the edges of the work area.
+    pub outer_gap: Metric,
+    /// Space between adjacent windows.
+    pub inner_gap: Metric,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            outer_gap: Metric::Absolute(0.0),
+            inner_gap: Metric::Absolute(0.0),
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Converts the gaps to pixels for a work area. Percentages are of the work area's width
+    /// for horizontal gaps, and of its height for vertical ones.
+    pub fn resolve(&self, work_area: &Rect) -> Gaps {
+        let (width, height) = work_area.wh();
+
+        Gaps {
+            outer_horizontal: self.outer_gap.resolve(width),
+            outer_vertical: self.outer_gap.resolve(height),
+            inner_horizontal: self.inner_gap.resolve(width),
+            inner_vertical: self.inner_gap.resolve(height),
+        }
+    }
+}
+
+/// How windows are arranged when they are tiled.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TilingLayout {
+    /// Master windows on the left, taking up `ratio` of the width, with the rest stacked on
+    /// the right.
+    MasterStack {
+        #[serde(default = "default_tiling_ratio")]
+        ratio: f32,
+        #[serde(default = "default_tiling_masters")]
+        masters: usize,
+    },
+    Columns,
+    Rows,
+    /// Every window fills the work area.
+    Monocle,
+    /// Each window takes `ratio` of the space left over by the windows before it.
+    Bsp {
+        #[serde(default = "default_tiling_ratio")]
+        ratio: f32,
+    },
+}
+
+impl Default for TilingLayout {
+    fn default() -> Self {
+        TilingLayout::MasterStack {
+            ratio: default_tiling_ratio(),
+            masters: default_tiling_masters(),
+        }
+    }
+}
+
+fn default_tiling_ratio() -> f32 {
+    0.5
+}
+
+fn default_tiling_masters() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TilingConfig {
+    /// Whether to tile every monitor when whimsy starts and whenever the configuration is
+    /// reloaded, and to retile a monitor whenever a window is opened, closed, minimized or
+    /// restored on it. The `tile` action works either way.
+    pub enabled: bool,
+    pub layout: TilingLayout,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Config {
+    /// Other configuration files to read before this one, relative to this one. Entries can
+    /// be globs, such as `conf.d/*.yaml`, which match files in name order.
+    pub include: Vec<String>,
     pub directives: ConfigDirectives,
+    pub layout: LayoutConfig,
+    pub tiling: TilingConfig,
+    pub rules: Vec<Rule>,
+    pub ignore: IgnoreConfig,
     pub bindings: Vec<Binding>,
+    pub modes: BTreeMap<String, Mode>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            include: Vec::new(),
             directives: ConfigDirectives::default(),
+            layout: LayoutConfig::default(),
+            tiling: TilingConfig::default(),
+            rules: Vec::new(),
+            ignore: IgnoreConfig::default(),
             bindings: vec![
                 Binding {
                     key: keybind::Key::Left,
                     modifiers: vec![keybind::Modifier::Super, keybind::Modifier::Shift],
+                    then: Vec::new(),
                     action: Action::Push {
                         direction: Direction::Left,
-                        fraction: 2.0,
+                        fraction: Fraction::Single(2.0),
                     },
                 },
                 Binding {
@@ -98,23 +685,239 @@ impl Default for Config {
                         keybind::Modifier::Shift,
                         keybind::Modifier::Alt,
                     ],
+                    then: Vec::new(),
                     action: Action::Nudge {
                         direction: Direction::Left,
                         distance: Metric::Absolute(100.0),
                     },
                 },
             ],
+            modes: BTreeMap::new(),
         }
     }
 }
 
-pub fn read_config_from_file(path: &dyn AsRef<Path>) -> Result<Option<Config>, ConfigReadError> {
-    if !path.as_ref().exists() {
+/// A configuration along with every file it was read from, in the order they were read.
+#[derive(Debug)]
+pub struct LoadedConfig {
+    pub config: Config,
+    pub files: Vec<PathBuf>,
+}
+
+/// Reads a configuration file and every file it includes. Included files are read first, in
+/// the order they are listed, and each file overrides the ones before it:
+///
+/// - Sections that are mappings, such as `directives`, are overridden key by key.
+/// - A binding replaces an earlier binding for the same keys, and other bindings are added
+///   after the earlier ones. Bindings in modes of the same name are merged the same way.
+/// - Rules come before earlier rules, so that they are matched first.
+/// - Anything else replaces what came before it.
+pub fn load_config(path: &dyn AsRef<Path>) -> Result<Option<LoadedConfig>, ConfigReadError> {
+    let path = path.as_ref();
+    if !path.exists() {
         return Ok(None);
     }
 
-    let config_string = std::fs::read_to_string(path).map_err(|e| ConfigReadError::IoError(e))?;
-    serde_yaml::from_str(&config_string).map_err(|e| ConfigReadError::DeserializeError(e))
+    let mut files = Vec::new();
+    let document = load_document(path, &mut Vec::new(), &mut files)?;
+    let config =
+        serde_yaml::from_value(document).map_err(|error| ConfigReadError::DeserializeError {
+            path: path.to_owned(),
+            error,
+        })?;
+
+    Ok(Some(LoadedConfig { config, files }))
+}
+
+pub fn read_config_from_file(path: &dyn AsRef<Path>) -> Result<Option<Config>, ConfigReadError> {
+    Ok(load_config(path)?.map(|loaded| loaded.config))
+}
+
+/// Reads one configuration file and merges the files it includes into it. `including` holds
+/// the files that are part way through being read, to catch files that include each other.
+fn load_document(
+    path: &Path,
+    including: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<serde_yaml::Value, ConfigReadError> {
+    let io_error = |error| ConfigReadError::IoError {
+        path: path.to_owned(),
+        error,
+    };
+    let deserialize_error = |error| ConfigReadError::DeserializeError {
+        path: path.to_owned(),
+        error,
+    };
+
+    let canonical = path.canonicalize().map_err(io_error)?;
+    if including.contains(&canonical) {
+        let mut cycle = including.clone();
+        cycle.push(canonical);
+        return Err(ConfigReadError::IncludeCycle(cycle));
+    }
+
+    // Every file has to be a valid configuration on its own, so that mistakes are reported
+    // against the file they are in rather than the merged result.
+    let mut source = std::fs::read_to_string(path).map_err(io_error)?;
+    if is_blank(&source) {
+        source = String::from("{}");
+    }
+
+    let config: Config = serde_yaml::from_str(&source).map_err(deserialize_error)?;
+    let mut document: serde_yaml::Value =
+        serde_yaml::from_str(&source).map_err(deserialize_error)?;
+
+    including.push(canonical.clone());
+    let mut merged = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    for pattern in &config.include {
+        for included in resolve_include(path, pattern)? {
+            merged = merge_documents(merged, load_document(&included, including, files)?);
+        }
+    }
+    including.pop();
+
+    if !files.contains(&canonical) {
+        files.push(canonical);
+    }
+
+    if let serde_yaml::Value::Mapping(mapping) = &mut document {
+        mapping.remove(&"include".into());
+    }
+
+    Ok(merge_documents(merged, document))
+}
+
+/// Whether a file has nothing but comments in it. serde_yaml fails on these, but they are
+/// handy as placeholders for files that are included.
+pub(crate) fn is_blank(source: &str) -> bool {
+    source.lines().all(|line| {
+        let line = line.trim();
+        line.is_empty() || line.starts_with('#')
+    })
+}
+
+/// Finds the files an `include` entry refers to. Globs may match no files at all, but a
+/// plain path has to exist, and a directory that a glob is matched against has to be
+/// readable.
+fn resolve_include(including_file: &Path, pattern: &str) -> Result<Vec<PathBuf>, ConfigReadError> {
+    // The parent of a bare file name is empty, which cannot be listed.
+    let directory = match including_file.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let full_path = directory.join(pattern);
+    let is_glob = |text: &str| text.contains('*') || text.contains('?');
+
+    let mut candidates = vec![PathBuf::new()];
+    for component in full_path.components() {
+        let name = component.as_os_str().to_string_lossy();
+        if !is_glob(&name) {
+            for candidate in &mut candidates {
+                candidate.push(component);
+            }
+            continue;
+        }
+
+        let glob = Pattern::Glob(name.into_owned());
+        let mut matches = Vec::new();
+        for directory in candidates.iter().filter(|candidate| candidate.is_dir()) {
+            let io_error = |error| ConfigReadError::IoError {
+                path: directory.clone(),
+                error,
+            };
+
+            for entry in std::fs::read_dir(directory).map_err(io_error)? {
+                let entry = entry.map_err(io_error)?;
+                if glob.matches(&entry.file_name().to_string_lossy()) {
+                    matches.push(entry.path());
+                }
+            }
+        }
+        matches.sort();
+        candidates = matches;
+    }
+
+    if !is_glob(pattern) && !full_path.is_file() {
+        return Err(ConfigReadError::MissingInclude {
+            path: including_file.to_owned(),
+            pattern: pattern.to_owned(),
+        });
+    }
+
+    candidates.retain(|candidate| candidate.is_file());
+    Ok(candidates)
+}
+
+/// Overrides one configuration document with another, as described in [`load_config`].
+fn merge_documents(base: serde_yaml::Value, overlay: serde_yaml::Value) -> serde_yaml::Value {
+    use serde_yaml::Value;
+
+    let (mut base, overlay) = match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => (base, overlay),
+        // An empty file is null, and overrides nothing.
+        (base, Value::Null) => return base,
+        (_, overlay) => return overlay,
+    };
+
+    for (key, value) in overlay {
+        let merged = match (key.as_str(), base.remove(&key), value) {
+            (Some("bindings"), Some(Value::Sequence(bindings)), Value::Sequence(overlay)) => {
+                Value::Sequence(merge_bindings(bindings, overlay))
+            }
+            (Some("rules"), Some(Value::Sequence(rules)), Value::Sequence(mut overlay)) => {
+                overlay.extend(rules);
+                Value::Sequence(overlay)
+            }
+            (Some("modes"), Some(Value::Mapping(mut modes)), Value::Mapping(overlay)) => {
+                for (name, mode) in overlay {
+                    let mode = match modes.remove(&name) {
+                        Some(base_mode) => merge_documents(base_mode, mode),
+                        None => mode,
+                    };
+                    modes.insert(name, mode);
+                }
+                Value::Mapping(modes)
+            }
+            (_, Some(Value::Mapping(mut section)), Value::Mapping(overlay)) => {
+                for (key, value) in overlay {
+                    section.insert(key, value);
+                }
+                Value::Mapping(section)
+            }
+            (_, _, value) => value,
+        };
+
+        base.insert(key, merged);
+    }
+
+    Value::Mapping(base)
+}
+
+/// Adds bindings to a list, replacing any binding for the same keys.
+fn merge_bindings(
+    mut bindings: Vec<serde_yaml::Value>,
+    overlay: Vec<serde_yaml::Value>,
+) -> Vec<serde_yaml::Value> {
+    // Every file has been deserialized on its own by now, so every binding is valid.
+    let sequence = |binding: &serde_yaml::Value| {
+        serde_yaml::from_value::<Binding>(binding.clone())
+            .ok()
+            .map(|binding| binding.sequence())
+    };
+
+    for binding in overlay {
+        let keys = sequence(&binding);
+        let existing = bindings
+            .iter()
+            .position(|existing| keys.is_some() && sequence(existing) == keys);
+
+        match existing {
+            Some(index) => bindings[index] = binding,
+            None => bindings.push(binding),
+        }
+    }
+
+    bindings
 }
 
 pub fn create_default_config() -> std::io::Result<()> {
@@ -122,7 +925,301 @@ pub fn create_default_config() -> std::io::Result<()> {
     let default_path: &PathBuf = &DEFAULT_CONFIG_PATH;
     // This should always succeed; the default config should always be representable.
     let config_string = serde_yaml::to_string(&default_config).unwrap();
-    std::fs::create_dir_all(&default_path.parent().unwrap())?;
-    std::fs::write(&default_path, &config_string)?;
+    std::fs::create_dir_all(default_path.parent().unwrap())?;
+    std::fs::write(default_path, &config_string)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Action {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn short_form_fills_fields_in_order() {
+        assert_eq!(
+            parse("push left 2"),
+            Action::Push {
+                direction: Direction::Left,
+                fraction: Fraction::Single(2.0),
+            }
+        );
+        assert_eq!(
+            parse("nudge up 10%"),
+            Action::Nudge {
+                direction: Direction::Up,
+                distance: Metric::Percent(0.1),
+            }
+        );
+        assert_eq!(
+            parse("move-to-monitor 1"),
+            Action::MoveToMonitor {
+                target: MonitorTarget::Index(1),
+            }
+        );
+        assert_eq!(parse("switch-workspace 3"), Action::SwitchWorkspace(3));
+        assert_eq!(parse("maximize"), Action::Maximize);
+    }
+
+    #[test]
+    fn short_form_keeps_bracketed_lists_together() {
+        let cycle = Action::Push {
+            direction: Direction::Left,
+            fraction: Fraction::Cycle(vec![2.0, 3.0, 1.5]),
+        };
+
+        assert_eq!(parse("push left [2, 3, 1.5]"), cycle);
+        assert_eq!(parse("push left [2,3,1.5]"), cycle);
+        assert_eq!(parse("push  left  [ 2 , 3 , 1.5 ] "), cycle);
+    }
+
+    #[test]
+    fn short_form_rest_takes_every_remaining_word() {
+        assert_eq!(
+            parse("save-layout two  monitors"),
+            Action::SaveLayout {
+                name: "two monitors".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn short_form_rejects_extra_words() {
+        assert!(matches!(
+            "push left [2, 3] 4".parse::<Action>(),
+            Err(ActionParseError::TooManyArguments(name, 2)) if name == "push"
+        ));
+        assert!(matches!(
+            "  ".parse::<Action>(),
+            Err(ActionParseError::Empty)
+        ));
+    }
+
+    #[test]
+    fn yaml_and_json_are_accepted_too() {
+        let cycle = Action::Push {
+            direction: Direction::Right,
+            fraction: Fraction::Cycle(vec![2.0, 3.0]),
+        };
+
+        assert_eq!(parse("{push: {direction: right, fraction: [2, 3]}}"), cycle);
+        assert_eq!(
+            parse(r#"{"push": {"direction": "right", "fraction": [2, 3]}}"#),
+            cycle
+        );
+    }
+
+    /// A directory of configuration files that is removed when dropped.
+    struct Files(PathBuf);
+
+    impl Files {
+        fn new(name: &str, files: &[(&str, &str)]) -> Files {
+            let root =
+                std::env::temp_dir().join(format!("whimsy-test-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&root);
+
+            for (path, contents) in files {
+                let path = root.join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, contents).unwrap();
+            }
+
+            Files(root)
+        }
+
+        fn path(&self, path: &str) -> PathBuf {
+            self.0.join(path).canonicalize().unwrap()
+        }
+
+        fn load(&self) -> LoadedConfig {
+            load_config(&self.0.join("whimsy.yaml")).unwrap().unwrap()
+        }
+    }
+
+    impl Drop for Files {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn keys(bindings: &[Binding]) -> Vec<(keybind::Key, &Action)> {
+        bindings
+            .iter()
+            .map(|binding| (binding.key, &binding.action))
+            .collect()
+    }
+
+    #[test]
+    fn globs_include_files_in_name_order() {
+        let files = Files::new(
+            "include-order",
+            &[
+                ("whimsy.yaml", "include: [conf.d/*.yaml]\nbindings: []"),
+                ("conf.d/20-b.yaml", "tiling: {layout: rows}"),
+                ("conf.d/10-a.yaml", "tiling: {layout: columns}"),
+                ("conf.d/30-c.yml", "tiling: {layout: monocle}"),
+            ],
+        );
+
+        let loaded = files.load();
+
+        // The later file wins, and files the glob does not match are left out.
+        assert_eq!(loaded.config.tiling.layout, TilingLayout::Rows);
+        assert_eq!(
+            loaded.files,
+            vec![
+                files.path("conf.d/10-a.yaml"),
+                files.path("conf.d/20-b.yaml"),
+                files.path("whimsy.yaml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn including_files_override_included_ones() {
+        let files = Files::new(
+            "include-merge",
+            &[
+                (
+                    "whimsy.yaml",
+                    "
+include: [base.yaml]
+directives:
+  sequence-timeout: 500
+bindings:
+  - {key: left, modifiers: [alt], action: maximize}
+  - {key: down, modifiers: [alt], action: restore}
+rules:
+  - {match: {class: {exact: Notepad}}, action: ignore}
+modes:
+  resize:
+    bindings:
+      - {key: escape, modifiers: [], action: exit-mode}
+",
+                ),
+                (
+                    "base.yaml",
+                    "
+directives:
+  live-reload: true
+  sequence-timeout: 2000
+bindings:
+  - {key: left, modifiers: [alt], action: undo}
+  - {key: right, modifiers: [alt], action: redo}
+rules:
+  - {match: {class: {exact: Edit}}, action: float}
+modes:
+  resize:
+    bindings:
+      - {key: escape, modifiers: [], action: tile}
+      - {key: left, modifiers: [], action: undo}
+  move:
+    bindings: []
+",
+                ),
+            ],
+        );
+
+        let config = files.load().config;
+
+        // Directives are overridden key by key.
+        assert!(config.directives.live_reload_configuration);
+        assert_eq!(config.directives.sequence_timeout_ms, 500);
+
+        // Bindings for the same keys are replaced in place, and new ones added after.
+        assert_eq!(
+            keys(&config.bindings),
+            vec![
+                (keybind::Key::Left, &Action::Maximize),
+                (keybind::Key::Right, &Action::Redo),
+                (keybind::Key::Down, &Action::Restore),
+            ]
+        );
+
+        // Rules of the including file are matched first.
+        assert_eq!(
+            config
+                .rules
+                .iter()
+                .map(|rule| &rule.action)
+                .collect::<Vec<_>>(),
+            vec![
+                &RuleAction::Behaviour(WindowBehaviour::Ignore),
+                &RuleAction::Behaviour(WindowBehaviour::Float),
+            ]
+        );
+
+        // Modes are merged by name, and their bindings like the top-level ones.
+        assert_eq!(
+            config.modes.keys().collect::<Vec<_>>(),
+            vec!["move", "resize"]
+        );
+        assert_eq!(
+            keys(&config.modes["resize"].bindings),
+            vec![
+                (keybind::Key::Escape, &Action::ExitMode),
+                (keybind::Key::Left, &Action::Undo),
+            ]
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_reported() {
+        let files = Files::new(
+            "include-cycle",
+            &[
+                ("whimsy.yaml", "include: [a.yaml]"),
+                ("a.yaml", "include: [b.yaml]"),
+                ("b.yaml", "include: [a.yaml]"),
+            ],
+        );
+
+        match load_config(&files.0.join("whimsy.yaml")) {
+            Err(ConfigReadError::IncludeCycle(cycle)) => assert_eq!(
+                cycle,
+                vec![
+                    files.path("whimsy.yaml"),
+                    files.path("a.yaml"),
+                    files.path("b.yaml"),
+                    files.path("a.yaml"),
+                ]
+            ),
+            other => panic!("expected an include cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_plain_includes_are_reported() {
+        let files = Files::new(
+            "include-missing",
+            &[("whimsy.yaml", "include: [missing.yaml, missing/*.yaml]")],
+        );
+
+        match load_config(&files.0.join("whimsy.yaml")) {
+            Err(ConfigReadError::MissingInclude { path, pattern }) => {
+                assert_eq!(path, files.0.join("whimsy.yaml"));
+                assert_eq!(pattern, "missing.yaml");
+            }
+            other => panic!("expected a missing include, got {:?}", other),
+        }
+
+        // A glob that matches nothing is fine.
+        let files = Files::new(
+            "include-empty-glob",
+            &[("whimsy.yaml", "include: [missing/*.yaml, '*.yml']")],
+        );
+        assert_eq!(files.load().files, vec![files.path("whimsy.yaml")]);
+    }
+
+    #[test]
+    fn globs_are_relative_to_the_working_directory_for_bare_file_names() {
+        // Tests run in the package directory.
+        assert_eq!(
+            resolve_include(Path::new("whimsy.yaml"), "Cargo.to?l").unwrap(),
+            vec![Path::new(".").join("Cargo.toml")]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::backend::{MonitorId, WindowId};
+use crate::keybind::{Key, Modifier};
+use crate::snapshot::SnapshotError;
+
+/// A failed Win32 API call, as reported by `GetLastError`.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{api} failed with OS error {code}: {message}")]
+pub struct Win32Error {
+    /// The name of the API function that failed.
+    pub api: &'static str,
+    pub code: u32,
+    /// The system's description of the error code.
+    pub message: String,
+}
+
+#[cfg(windows)]
+impl Win32Error {
+    /// Captures the calling thread's last error code. This must be called immediately after
+    /// the failing API call, before anything else can overwrite the error code.
+    pub fn last_error(api: &'static str) -> Win32Error {
+        let code = unsafe { winapi::um::errhandlingapi::GetLastError() };
+
+        Win32Error {
+            api,
+            code,
+            message: format_error_message(code),
+        }
+    }
+
+    /// Wraps a failed `HRESULT` from a COM-style API.
+    pub fn from_hresult(api: &'static str, result: i32) -> Win32Error {
+        // HRESULTs are conventionally displayed as unsigned hex, and FormatMessage knows
+        // about the system ones.
+        let code = result as u32;
+
+        Win32Error {
+            api,
+            code,
+            message: format_error_message(code),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn format_error_message(code: u32) -> String {
+    use winapi::um::winbase;
+
+    let mut buffer = [0u16; 512];
+    let length = unsafe {
+        winbase::FormatMessageW(
+            winbase::FORMAT_MESSAGE_FROM_SYSTEM | winbase::FORMAT_MESSAGE_IGNORE_INSERTS,
+            std::ptr::null(),
+            code,
+            0,
+            buffer.as_mut_ptr(),
+            // cast is safe, the buffer is far smaller than u32::MAX
+            buffer.len() as u32,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if length == 0 {
+        return String::from("unknown error");
+    }
+
+    // System messages end in a CRLF, which is not useful to us.
+    String::from_utf16_lossy(&buffer[..length as usize])
+        .trim_end()
+        .to_owned()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Win32(Win32Error),
+    #[error("could not register hotkey {key:?} with modifiers {modifiers:?}: {error}")]
+    RegisterHotkey {
+        key: Key,
+        modifiers: Vec<Modifier>,
+        error: Win32Error,
+    },
+    #[error("operation on window {window:?} failed: {error}")]
+    Window { window: WindowId, error: Win32Error },
+    #[error("operation on monitor {monitor:?} failed: {error}")]
+    Monitor {
+        monitor: MonitorId,
+        error: Win32Error,
+    },
+    #[error("hotkey with internal ID {0} was fired, but it is not registered")]
+    UnregisteredHotkey(i32),
+    #[error("no hotkey with internal ID {0} is registered")]
+    NoSuchHotkey(i32),
+    #[error("every hotkey ID is in use")]
+    NoHotkeyIds,
+    #[error("window {0:?} does not exist")]
+    NoSuchWindow(WindowId),
+    #[error("monitor {0:?} does not exist")]
+    NoSuchMonitor(MonitorId),
+    #[error("there are no monitors")]
+    NoMonitors,
+    #[error("{0}")]
+    Snapshot(SnapshotError),
+}
+
+/// `ERROR_HOTKEY_ALREADY_REGISTERED`, returned by `RegisterHotKey` when the combination is
+/// already taken.
+const ERROR_HOTKEY_ALREADY_REGISTERED: u32 = 1409;
+
+impl Error {
+    /// Whether this error came from a hotkey combination that is already registered, either
+    /// by another application or by whimsy itself.
+    pub fn is_hotkey_conflict(&self) -> bool {
+        matches!(
+            self,
+            Error::RegisterHotkey { error, .. } if error.code == ERROR_HOTKEY_ALREADY_REGISTERED
+        )
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
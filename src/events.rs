@@ -0,0 +1,127 @@
+//! Notifications about windows on the desktop. Win32 reports these through a WinEvent hook;
+//! the hook forwards the ones whimsy cares about to the message loop, so that they are
+//! handled alongside hotkeys.
+use std::cell::Cell;
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::LONG;
+use winapi::shared::windef::{HWINEVENTHOOK, HWND};
+use winapi::um::winuser;
+
+use crate::backend::WindowId;
+use crate::error::{Error, Result, Win32Error};
+use crate::keybind::MessageLoopHandle;
+
+thread_local! {
+    /// Where the hook on this thread forwards events to. WinEvent callbacks take no context
+    /// argument, and out-of-context hooks call back on the thread that installed them.
+    static EVENT_TARGET: Cell<Option<MessageLoopHandle>> = const { Cell::new(None) };
+}
+
+/// The ranges of events that the hook listens to. Minimizing and restoring come from a
+/// different range than showing and hiding, and the events between them are not wanted.
+const EVENT_RANGES: &[(DWORD, DWORD)] = &[
+    (winuser::EVENT_OBJECT_DESTROY, winuser::EVENT_OBJECT_HIDE),
+    (
+        winuser::EVENT_SYSTEM_MINIMIZESTART,
+        winuser::EVENT_SYSTEM_MINIMIZEEND,
+    ),
+];
+
+/// Forwards windows being shown and hidden to a message loop until dropped.
+#[derive(Debug)]
+pub struct WindowEventHook {
+    handles: Vec<HWINEVENTHOOK>,
+}
+
+impl WindowEventHook {
+    /// Installs the hook. This must be called from the thread that polls `message_loop`,
+    /// since that is the thread the hook calls back on.
+    pub fn install(message_loop: MessageLoopHandle) -> Result<WindowEventHook> {
+        EVENT_TARGET.with(|target| target.set(Some(message_loop)));
+
+        // Dropping the hook unhooks any ranges that were hooked before a failure.
+        let mut hook = WindowEventHook {
+            handles: Vec::new(),
+        };
+
+        for &(first, last) in EVENT_RANGES {
+            let handle = unsafe {
+                winuser::SetWinEventHook(
+                    first,
+                    last,
+                    std::ptr::null_mut(),
+                    Some(on_window_event),
+                    0,
+                    0,
+                    winuser::WINEVENT_OUTOFCONTEXT | winuser::WINEVENT_SKIPOWNPROCESS,
+                )
+            };
+
+            if handle.is_null() {
+                return Err(Error::Win32(Win32Error::last_error(
+                    "winuser::SetWinEventHook",
+                )));
+            }
+
+            hook.handles.push(handle);
+        }
+
+        Ok(hook)
+    }
+}
+
+impl Drop for WindowEventHook {
+    fn drop(&mut self) {
+        for &handle in &self.handles {
+            unsafe {
+                winuser::UnhookWinEvent(handle);
+            }
+        }
+
+        EVENT_TARGET.with(|target| target.set(None));
+    }
+}
+
+unsafe extern "system" fn on_window_event(
+    _: HWINEVENTHOOK,
+    event: DWORD,
+    handle: HWND,
+    object: LONG,
+    child: LONG,
+    _: DWORD,
+    _: DWORD,
+) {
+    // These events are also raised for carets, cursors and controls inside windows; only
+    // top-level windows are interesting. A destroyed window may already be gone, so there
+    // is no telling whether it was top-level; the executor ignores windows it does not know.
+    if handle.is_null()
+        || object != winuser::OBJID_WINDOW
+        || child != winuser::CHILDID_SELF
+        || (event != winuser::EVENT_OBJECT_DESTROY
+            && winuser::GetAncestor(handle, winuser::GA_ROOT) != handle)
+    {
+        return;
+    }
+
+    let window = WindowId(handle as isize);
+    let target = match EVENT_TARGET.with(Cell::get) {
+        Some(target) => target,
+        None => return,
+    };
+
+    let result = match event {
+        winuser::EVENT_OBJECT_SHOW | winuser::EVENT_SYSTEM_MINIMIZEEND => {
+            target.notify_window_shown(window)
+        }
+        winuser::EVENT_OBJECT_HIDE | winuser::EVENT_SYSTEM_MINIMIZESTART => {
+            target.notify_window_hidden(window)
+        }
+        winuser::EVENT_OBJECT_DESTROY => target.notify_window_destroyed(window),
+        _ => return,
+    };
+
+    if let Err(error) = result {
+        log::error!("Unable to forward a window event: {}", error);
+    }
+}
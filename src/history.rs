@@ -0,0 +1,216 @@
+//! Bookkeeping for the rects that whimsy has moved windows away from, so that moves can be
+//! undone.
+use std::collections::{HashMap, VecDeque};
+
+use crate::backend::WindowId;
+use crate::window::Rect;
+
+/// The number of moves remembered per window.
+pub const DEFAULT_HISTORY_LIMIT: usize = 32;
+
+#[derive(Debug, Clone)]
+struct WindowHistory {
+    /// The rect the window had before whimsy first moved it.
+    original: Rect,
+    /// Rects to return to on undo, most recent last.
+    undo: VecDeque<Rect>,
+    /// Rects to return to on redo, most recent last.
+    redo: Vec<Rect>,
+}
+
+#[derive(Debug)]
+pub struct History {
+    windows: HashMap<WindowId, WindowHistory>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> History {
+        History {
+            windows: HashMap::new(),
+            limit,
+        }
+    }
+
+    /// Records that a window was moved away from `from`. This discards anything that could
+    /// have been redone.
+    pub fn record(&mut self, window: WindowId, from: Rect) {
+        let limit = self.limit;
+        let history = self.windows.entry(window).or_insert_with(|| WindowHistory {
+            original: from,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        });
+
+        history.redo.clear();
+        history.undo.push_back(from);
+        while history.undo.len() > limit {
+            history.undo.pop_front();
+        }
+    }
+
+    /// Returns the rect that undoing would return the window to, without undoing anything.
+    pub fn peek_undo(&self, window: WindowId) -> Option<Rect> {
+        self.windows.get(&window)?.undo.back().copied()
+    }
+
+    /// Returns the rect that redoing would return the window to, without redoing anything.
+    pub fn peek_redo(&self, window: WindowId) -> Option<Rect> {
+        self.windows.get(&window)?.redo.last().copied()
+    }
+
+    /// Returns the rect to undo to, remembering `current` so that it can be redone.
+    pub fn undo(&mut self, window: WindowId, current: Rect) -> Option<Rect> {
+        let history = self.windows.get_mut(&window)?;
+        let previous = history.undo.pop_back()?;
+        history.redo.push(current);
+        Some(previous)
+    }
+
+    /// Returns the rect to redo to, remembering `current` so that it can be undone again.
+    pub fn redo(&mut self, window: WindowId, current: Rect) -> Option<Rect> {
+        let history = self.windows.get_mut(&window)?;
+        let next = history.redo.pop()?;
+        history.undo.push_back(current);
+        Some(next)
+    }
+
+    /// Returns the rect the window had before whimsy first moved it.
+    pub fn original(&self, window: WindowId) -> Option<Rect> {
+        self.windows.get(&window).map(|history| history.original)
+    }
+
+    /// Forgets everything about a window, e.g. because it was destroyed.
+    pub fn forget(&mut self, window: WindowId) {
+        self.windows.remove(&window);
+    }
+
+    /// Forgets every window for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(WindowId) -> bool) {
+        self.windows.retain(|&window, _| keep(window));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: WindowId = WindowId(1);
+    const OTHER: WindowId = WindowId(2);
+
+    /// Distinct rects, one per step.
+    fn rect(step: i32) -> Rect {
+        Rect::xywh(step * 10, 0, 100, 100)
+    }
+
+    #[test]
+    fn undo_and_redo_walk_back_and_forth() {
+        let mut history = History::default();
+        history.record(WINDOW, rect(0));
+        history.record(WINDOW, rect(1));
+        // The window is now at rect(2).
+
+        assert_eq!(history.peek_undo(WINDOW), Some(rect(1)));
+        assert_eq!(history.undo(WINDOW, rect(2)), Some(rect(1)));
+        assert_eq!(history.undo(WINDOW, rect(1)), Some(rect(0)));
+        assert_eq!(history.undo(WINDOW, rect(0)), None);
+
+        assert_eq!(history.peek_redo(WINDOW), Some(rect(1)));
+        assert_eq!(history.redo(WINDOW, rect(0)), Some(rect(1)));
+        assert_eq!(history.redo(WINDOW, rect(1)), Some(rect(2)));
+        assert_eq!(history.redo(WINDOW, rect(2)), None);
+
+        assert_eq!(history.peek_undo(WINDOW), Some(rect(1)));
+        assert_eq!(history.original(WINDOW), Some(rect(0)));
+    }
+
+    #[test]
+    fn recording_discards_redo() {
+        let mut history = History::default();
+        history.record(WINDOW, rect(0));
+        history.record(WINDOW, rect(1));
+        history.undo(WINDOW, rect(2));
+
+        history.record(WINDOW, rect(1));
+
+        assert_eq!(history.peek_redo(WINDOW), None);
+        assert_eq!(history.undo(WINDOW, rect(3)), Some(rect(1)));
+        assert_eq!(history.undo(WINDOW, rect(1)), Some(rect(0)));
+    }
+
+    #[test]
+    fn windows_have_separate_histories() {
+        let mut history = History::default();
+        history.record(WINDOW, rect(0));
+        history.record(OTHER, rect(5));
+
+        assert_eq!(history.undo(OTHER, rect(6)), Some(rect(5)));
+        assert_eq!(history.peek_undo(WINDOW), Some(rect(0)));
+        assert_eq!(history.peek_undo(WindowId(3)), None);
+        assert_eq!(history.original(WindowId(3)), None);
+    }
+
+    #[test]
+    fn only_the_most_recent_moves_are_kept() {
+        let mut history = History::default();
+        let moves = DEFAULT_HISTORY_LIMIT as i32 + 8;
+        for step in 0..moves {
+            history.record(WINDOW, rect(step));
+        }
+
+        let mut current = rect(moves);
+        let mut undone = Vec::new();
+        while let Some(previous) = history.undo(WINDOW, current) {
+            undone.push(previous);
+            current = previous;
+        }
+
+        assert_eq!(undone.len(), DEFAULT_HISTORY_LIMIT);
+        assert_eq!(undone.first(), Some(&rect(moves - 1)));
+        assert_eq!(undone.last(), Some(&rect(8)));
+        // The original rect is kept even once its move has been dropped.
+        assert_eq!(history.original(WINDOW), Some(rect(0)));
+
+        let mut history = History::new(2);
+        for step in 0..5 {
+            history.record(WINDOW, rect(step));
+        }
+        assert_eq!(history.undo(WINDOW, rect(5)), Some(rect(4)));
+        assert_eq!(history.undo(WINDOW, rect(4)), Some(rect(3)));
+        assert_eq!(history.undo(WINDOW, rect(3)), None);
+    }
+
+    #[test]
+    fn forgotten_windows_have_no_history() {
+        let mut history = History::default();
+        history.record(WINDOW, rect(0));
+        history.record(OTHER, rect(5));
+
+        history.forget(WINDOW);
+        assert_eq!(history.peek_undo(WINDOW), None);
+        assert_eq!(history.original(WINDOW), None);
+        assert_eq!(history.peek_undo(OTHER), Some(rect(5)));
+
+        // A new window with the same handle starts afresh.
+        history.record(WINDOW, rect(2));
+        assert_eq!(history.original(WINDOW), Some(rect(2)));
+    }
+
+    #[test]
+    fn retain_forgets_windows_that_are_gone() {
+        let mut history = History::default();
+        history.record(WINDOW, rect(0));
+        history.record(OTHER, rect(5));
+
+        history.retain(|window| window == OTHER);
+
+        assert_eq!(history.peek_undo(WINDOW), None);
+        assert_eq!(history.original(OTHER), Some(rect(5)));
+    }
+}
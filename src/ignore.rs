@@ -0,0 +1,304 @@
+//! Windows that actions should never touch, such as the taskbar, the desktop and full-screen
+//! games. Like rules, this works on [`WindowInfo`] and does not touch the backend.
+use std::fmt;
+
+use crate::backend::WindowInfo;
+use crate::config::IgnoreConfig;
+use crate::window::Rect;
+
+/// Classes of shell windows that look like ordinary windows to Win32.
+pub const BUILTIN_IGNORED_CLASSES: &[&str] = &[
+    // The taskbar, on the primary monitor and on the others.
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    // The desktop.
+    "Progman",
+    "WorkerW",
+    // Shell surfaces such as the Start menu, search and the action center.
+    "Windows.UI.Core.CoreWindow",
+    "NotifyIconOverflowWindow",
+    "TopLevelWindowForOverflowXamlIsland",
+];
+
+/// Executables of the shell's UWP surfaces.
+pub const BUILTIN_IGNORED_EXECUTABLES: &[&str] = &[
+    "ShellExperienceHost.exe",
+    "StartMenuExperienceHost.exe",
+    "SearchApp.exe",
+    "SearchUI.exe",
+    "SearchHost.exe",
+    "LockApp.exe",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IgnoreReason {
+    /// A rule says to ignore the window.
+    Rule,
+    /// The window is part of the Windows shell.
+    Shell,
+    /// The window matches the configured ignore list.
+    Configured,
+    ToolWindow,
+    FixedSize,
+    /// The window has no title bar and covers its entire monitor, as full-screen games and
+    /// videos do.
+    Fullscreen,
+}
+
+impl fmt::Display for IgnoreReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IgnoreReason::Rule => "a rule ignores it",
+            IgnoreReason::Shell => "it is part of the Windows shell",
+            IgnoreReason::Configured => "it is on the ignore list",
+            IgnoreReason::ToolWindow => "it is a tool window",
+            IgnoreReason::FixedSize => "it cannot be resized",
+            IgnoreReason::Fullscreen => "it is full-screen",
+        })
+    }
+}
+
+/// Returns why actions should leave a window alone, if they should. `monitor_bounds` are
+/// the full bounds of the monitor the window is on.
+pub fn ignore_reason(
+    config: &IgnoreConfig,
+    info: &WindowInfo,
+    window_rect: &Rect,
+    monitor_bounds: &Rect,
+) -> Option<IgnoreReason> {
+    if config.builtin && is_shell_window(info) {
+        return Some(IgnoreReason::Shell);
+    }
+
+    if config.windows.iter().any(|criteria| criteria.matches(info)) {
+        return Some(IgnoreReason::Configured);
+    }
+
+    if config.tool_windows && info.tool_window {
+        return Some(IgnoreReason::ToolWindow);
+    }
+
+    if config.fixed_size && info.fixed_size {
+        return Some(IgnoreReason::FixedSize);
+    }
+
+    // Maximized windows can cover the monitor too when the taskbar hides itself, but they
+    // keep their title bar.
+    let covers_monitor = window_rect.intersection(monitor_bounds) == Some(*monitor_bounds);
+    if config.fullscreen && info.borderless && covers_monitor {
+        return Some(IgnoreReason::Fullscreen);
+    }
+
+    None
+}
+
+fn is_shell_window(info: &WindowInfo) -> bool {
+    let executable = info
+        .executable
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy());
+
+    BUILTIN_IGNORED_CLASSES.contains(&info.class.as_str())
+        || executable.is_some_and(|executable| {
+            BUILTIN_IGNORED_EXECUTABLES
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(&executable))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::WindowMatch;
+    use crate::rules::Pattern;
+
+    const MONITOR: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    const WINDOW: Rect = Rect {
+        left: 100,
+        top: 100,
+        right: 500,
+        bottom: 400,
+    };
+
+    fn info(class: &str, executable: &str) -> WindowInfo {
+        WindowInfo {
+            class: class.to_owned(),
+            title: "Title".to_owned(),
+            executable: Some(PathBuf::from(executable)),
+            ..WindowInfo::default()
+        }
+    }
+
+    fn reason(config: &IgnoreConfig, info: &WindowInfo, rect: &Rect) -> Option<IgnoreReason> {
+        ignore_reason(config, info, rect, &MONITOR)
+    }
+
+    #[test]
+    fn ordinary_windows_are_not_ignored() {
+        let notepad = info("Notepad", "C:/Windows/notepad.exe");
+
+        assert_eq!(reason(&IgnoreConfig::default(), &notepad, &WINDOW), None);
+    }
+
+    #[test]
+    fn shell_windows_are_ignored() {
+        let config = IgnoreConfig::default();
+
+        assert_eq!(
+            reason(
+                &config,
+                &info("Shell_TrayWnd", "C:/Windows/explorer.exe"),
+                &WINDOW
+            ),
+            Some(IgnoreReason::Shell)
+        );
+        // Executables are matched by file name, in any case.
+        assert_eq!(
+            reason(
+                &config,
+                &info(
+                    "Windows.UI.Core.CoreWindow2",
+                    "C:/Windows/SystemApps/searchhost.EXE"
+                ),
+                &WINDOW
+            ),
+            Some(IgnoreReason::Shell)
+        );
+
+        let config = IgnoreConfig {
+            builtin: false,
+            ..IgnoreConfig::default()
+        };
+        assert_eq!(
+            reason(
+                &config,
+                &info("Shell_TrayWnd", "C:/Windows/explorer.exe"),
+                &WINDOW
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn configured_windows_are_ignored() {
+        let config = IgnoreConfig {
+            windows: vec![
+                WindowMatch {
+                    class: Some(Pattern::Exact("Chrome_WidgetWin_1".to_owned())),
+                    ..WindowMatch::default()
+                },
+                WindowMatch {
+                    executable: Some(Pattern::Glob("steam*.exe".to_owned())),
+                    ..WindowMatch::default()
+                },
+            ],
+            ..IgnoreConfig::default()
+        };
+
+        assert_eq!(
+            reason(
+                &config,
+                &info("Chrome_WidgetWin_1", "C:/chrome.exe"),
+                &WINDOW
+            ),
+            Some(IgnoreReason::Configured)
+        );
+        assert_eq!(
+            reason(
+                &config,
+                &info("SDL_app", "D:/Steam/steamwebhelper.exe"),
+                &WINDOW
+            ),
+            Some(IgnoreReason::Configured)
+        );
+        assert_eq!(
+            reason(&config, &info("Notepad", "C:/Windows/notepad.exe"), &WINDOW),
+            None
+        );
+    }
+
+    #[test]
+    fn tool_windows_are_ignored() {
+        let palette = WindowInfo {
+            tool_window: true,
+            ..info("Palette", "C:/paint.exe")
+        };
+
+        assert_eq!(
+            reason(&IgnoreConfig::default(), &palette, &WINDOW),
+            Some(IgnoreReason::ToolWindow)
+        );
+
+        let config = IgnoreConfig {
+            tool_windows: false,
+            ..IgnoreConfig::default()
+        };
+        assert_eq!(reason(&config, &palette, &WINDOW), None);
+    }
+
+    #[test]
+    fn fixed_size_windows_are_only_ignored_when_asked() {
+        let dialog = WindowInfo {
+            fixed_size: true,
+            ..info("#32770", "C:/Windows/notepad.exe")
+        };
+
+        assert_eq!(reason(&IgnoreConfig::default(), &dialog, &WINDOW), None);
+
+        let config = IgnoreConfig {
+            fixed_size: true,
+            ..IgnoreConfig::default()
+        };
+        assert_eq!(
+            reason(&config, &dialog, &WINDOW),
+            Some(IgnoreReason::FixedSize)
+        );
+    }
+
+    #[test]
+    fn borderless_windows_covering_the_monitor_are_fullscreen() {
+        let config = IgnoreConfig::default();
+        let game = WindowInfo {
+            borderless: true,
+            ..info("UnityWndClass", "D:/Games/game.exe")
+        };
+
+        assert_eq!(
+            reason(&config, &game, &MONITOR),
+            Some(IgnoreReason::Fullscreen)
+        );
+        // Windows that spill over the edges of the monitor cover it too.
+        assert_eq!(
+            reason(&config, &game, &Rect::xyxy(-8, -8, 1928, 1088)),
+            Some(IgnoreReason::Fullscreen)
+        );
+
+        // Borderless but smaller than the monitor, such as a splash screen.
+        assert_eq!(reason(&config, &game, &WINDOW), None);
+        assert_eq!(reason(&config, &game, &Rect::xyxy(0, 0, 1920, 1040)), None);
+
+        // Covering the monitor with a title bar, as a maximized window with an auto-hiding
+        // taskbar does.
+        let maximized = WindowInfo {
+            borderless: false,
+            ..game.clone()
+        };
+        assert_eq!(reason(&config, &maximized, &MONITOR), None);
+
+        let config = IgnoreConfig {
+            fullscreen: false,
+            ..IgnoreConfig::default()
+        };
+        assert_eq!(reason(&config, &game, &MONITOR), None);
+    }
+}
@@ -0,0 +1,611 @@
+//! Control of a running whimsy by other processes. Clients connect to a local endpoint (a
+//! named pipe on Windows, a Unix domain socket elsewhere) and exchange requests and
+//! responses as JSON, one message per line.
+//!
+//! Connections are served on background threads, but actions have to be executed on the
+//! thread that owns the executor. Connection threads queue requests with a
+//! [`RequestSender`] and wait for the owning thread to answer them through the matching
+//! [`RequestQueue`]. Nothing in between cares about the transport, so a channel created
+//! with [`channel`] can be driven entirely in-process.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use crate::action::{ActionExecutor, ActionOutcome};
+use crate::backend::Backend;
+use crate::config::Action;
+use crate::keymap::Keymap;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IpcError {
+    #[error("could not communicate with whimsy: {0}")]
+    IoError(std::io::Error),
+    #[error("could not serialize message: {0}")]
+    SerializeError(serde_json::Error),
+    #[error("could not deserialize message: {0}")]
+    DeserializeError(serde_json::Error),
+    #[error("the connection was closed before a response was received")]
+    Disconnected,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Request {
+    /// Executes an action, as if a binding for it had been pressed.
+    Execute(Action),
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Response {
+    /// The action was executed, or skipped for the reason given in the outcome.
+    Executed(ActionOutcome),
+    /// The active mode after entering or exiting a mode.
+    Mode(String),
+    Error(String),
+}
+
+/// Returns where clients should connect to the running whimsy.
+pub fn default_endpoint() -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(r"\\.\pipe\whimsy")
+    } else {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("whimsy.sock")
+    }
+}
+
+/// Answers a request. Mode changes go to the keymap, since that is where modes live; every
+/// other action goes to the executor.
+pub fn handle_request<B: Backend>(
+    executor: &mut ActionExecutor<B>,
+    keymap: &mut Keymap,
+    request: Request,
+) -> Response {
+    match request {
+        Request::Execute(Action::EnterMode { mode }) => {
+            if !keymap.has_mode(&mode) {
+                return Response::Error(format!("there is no mode named {:?}", mode));
+            }
+
+            keymap.enter_mode(&mode);
+            Response::Mode(keymap.mode().to_owned())
+        }
+        Request::Execute(Action::ExitMode) => {
+            keymap.exit_mode();
+            Response::Mode(keymap.mode().to_owned())
+        }
+        Request::Execute(action) => match executor.execute(&action) {
+            Ok(outcome) => Response::Executed(outcome),
+            Err(error) => Response::Error(error.to_string()),
+        },
+    }
+}
+
+type QueuedRequest = (Request, mpsc::Sender<Response>);
+
+/// Queues requests for the thread that answers them. This can be cloned and shared between
+/// connection threads.
+#[derive(Clone)]
+pub struct RequestSender {
+    sender: mpsc::Sender<QueuedRequest>,
+    on_request: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl RequestSender {
+    /// Queues a request and blocks until it has been answered.
+    pub fn send(&self, request: Request) -> Result<Response, IpcError> {
+        let (respond, response) = mpsc::channel();
+        self.sender
+            .send((request, respond))
+            .map_err(|_| IpcError::Disconnected)?;
+        (self.on_request)();
+
+        response.recv().map_err(|_| IpcError::Disconnected)
+    }
+}
+
+/// The requests waiting to be answered.
+pub struct RequestQueue {
+    receiver: mpsc::Receiver<QueuedRequest>,
+}
+
+impl RequestQueue {
+    /// Answers every request that is waiting, without blocking.
+    pub fn handle_pending(&self, mut handler: impl FnMut(Request) -> Response) {
+        while let Ok((request, respond)) = self.receiver.try_recv() {
+            log::debug!("Handling IPC request {:?}", request);
+            // The client may have given up waiting; that is its problem.
+            let _ = respond.send(handler(request));
+        }
+    }
+}
+
+/// Creates a request queue. `on_request` is called whenever a request is queued, so that
+/// the thread that owns the queue can be woken up to answer it.
+pub fn channel<F>(on_request: F) -> (RequestSender, RequestQueue)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+
+    (
+        RequestSender {
+            sender,
+            on_request: Arc::new(on_request),
+        },
+        RequestQueue { receiver },
+    )
+}
+
+/// Reads one message. Returns `None` once the other end has closed the connection.
+pub fn read_message<T, R>(reader: &mut R) -> Result<Option<T>, IpcError>
+where
+    T: serde::de::DeserializeOwned,
+    R: BufRead,
+{
+    let mut line = String::new();
+    let length = reader.read_line(&mut line).map_err(IpcError::IoError)?;
+    if length == 0 {
+        return Ok(None);
+    }
+
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(IpcError::DeserializeError)
+}
+
+pub fn write_message<T, W>(writer: &mut W, message: &T) -> Result<(), IpcError>
+where
+    T: serde::Serialize,
+    W: Write,
+{
+    let mut line = serde_json::to_string(message).map_err(IpcError::SerializeError)?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(IpcError::IoError)
+}
+
+/// Answers requests from one client until it disconnects. A request that cannot be read
+/// is answered with an error rather than dropping the connection.
+pub fn serve_connection<R, W>(
+    reader: R,
+    mut writer: W,
+    requests: &RequestSender,
+) -> Result<(), IpcError>
+where
+    R: Read,
+    W: Write,
+{
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let response = match read_message::<Request, _>(&mut reader) {
+            Ok(Some(request)) => requests.send(request)?,
+            Ok(None) => return Ok(()),
+            Err(IpcError::DeserializeError(error)) => {
+                Response::Error(format!("invalid request: {}", error))
+            }
+            Err(error) => return Err(error),
+        };
+
+        write_message(&mut writer, &response)?;
+    }
+}
+
+/// Accepts connections on a background thread, serving each one on a thread of its own.
+/// The listening thread blocks waiting for clients, so it is left to stop with the process.
+pub struct Server {
+    endpoint: PathBuf,
+}
+
+impl Server {
+    pub fn endpoint(&self) -> &Path {
+        &self.endpoint
+    }
+}
+
+fn spawn_connection<R, W>(reader: R, writer: W, requests: RequestSender)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    std::thread::spawn(move || {
+        if let Err(error) = serve_connection(reader, writer, &requests) {
+            log::warn!("IPC connection failed: {}", error);
+        }
+    });
+}
+
+#[cfg(unix)]
+impl Server {
+    /// Starts listening. A socket left behind by a previous instance is replaced, but one
+    /// that another process is still listening on is not.
+    pub fn spawn(endpoint: PathBuf, requests: RequestSender) -> std::io::Result<Server> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        if endpoint.exists() {
+            if UnixStream::connect(&endpoint).is_ok() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    format!("{} is in use by another process", endpoint.display()),
+                ));
+            }
+
+            std::fs::remove_file(&endpoint)?;
+        }
+
+        let listener = UnixListener::bind(&endpoint)?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let accepted = stream.and_then(|stream| Ok((stream.try_clone()?, stream)));
+                match accepted {
+                    Ok((reader, writer)) => spawn_connection(reader, writer, requests.clone()),
+                    Err(error) => log::warn!("Unable to accept IPC connection: {}", error),
+                }
+            }
+        });
+
+        Ok(Server { endpoint })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.endpoint);
+    }
+}
+
+#[cfg(windows)]
+fn create_pipe_instance(endpoint: &Path, first: bool) -> std::io::Result<std::fs::File> {
+    use std::os::windows::prelude::*;
+    use winapi::um::{handleapi, namedpipeapi, winbase};
+
+    let mut name: Vec<u16> = endpoint.as_os_str().encode_wide().collect();
+    name.push(0);
+
+    // Only the first instance claims the name, so that a second whimsy fails to start its
+    // server instead of sharing the pipe with the first.
+    let mut open_mode = winbase::PIPE_ACCESS_DUPLEX;
+    if first {
+        open_mode |= winbase::FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+
+    let handle = unsafe {
+        namedpipeapi::CreateNamedPipeW(
+            name.as_ptr(),
+            open_mode,
+            winbase::PIPE_TYPE_BYTE
+                | winbase::PIPE_READMODE_BYTE
+                | winbase::PIPE_WAIT
+                | winbase::PIPE_REJECT_REMOTE_CLIENTS,
+            winbase::PIPE_UNLIMITED_INSTANCES,
+            4096,
+            4096,
+            0,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if handle == handleapi::INVALID_HANDLE_VALUE {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(unsafe { std::fs::File::from_raw_handle(handle as RawHandle) })
+}
+
+#[cfg(windows)]
+fn connect_pipe(pipe: &std::fs::File) -> std::io::Result<()> {
+    use std::os::windows::prelude::*;
+    use winapi::shared::winerror;
+    use winapi::um::namedpipeapi;
+
+    let connected =
+        unsafe { namedpipeapi::ConnectNamedPipe(pipe.as_raw_handle() as _, std::ptr::null_mut()) };
+
+    // A client that connects between the pipe being created and this call is reported as
+    // an error, but is connected all the same.
+    if connected == 0 {
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() != Some(winerror::ERROR_PIPE_CONNECTED as i32) {
+            return Err(error);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+impl Server {
+    /// Starts listening. Fails if another process already has a pipe with this name.
+    pub fn spawn(endpoint: PathBuf, requests: RequestSender) -> std::io::Result<Server> {
+        let mut pipe = create_pipe_instance(&endpoint, true)?;
+        let thread_endpoint = endpoint.clone();
+
+        std::thread::spawn(move || loop {
+            if let Err(error) = connect_pipe(&pipe) {
+                log::warn!("Unable to accept IPC connection: {}", error);
+            } else {
+                match pipe.try_clone() {
+                    Ok(reader) => spawn_connection(reader, pipe, requests.clone()),
+                    Err(error) => log::warn!("Unable to accept IPC connection: {}", error),
+                }
+            }
+
+            pipe = match create_pipe_instance(&thread_endpoint, false) {
+                Ok(pipe) => pipe,
+                Err(error) => {
+                    log::error!("Unable to keep listening for IPC connections: {}", error);
+                    return;
+                }
+            };
+        });
+
+        Ok(Server { endpoint })
+    }
+}
+
+/// Connects to the running whimsy and sends it a single request.
+pub fn send_request(endpoint: &Path, request: &Request) -> Result<Response, IpcError> {
+    #[cfg(unix)]
+    let stream = std::os::unix::net::UnixStream::connect(endpoint);
+    #[cfg(windows)]
+    let stream = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(endpoint);
+
+    let mut stream = stream.map_err(IpcError::IoError)?;
+    write_message(&mut stream, request)?;
+    read_message(&mut BufReader::new(stream))?.ok_or(IpcError::Disconnected)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::backend::{VirtualDesktop, WindowId};
+    use crate::config::Config;
+    use crate::keymap::DEFAULT_MODE;
+    use crate::window::Rect;
+
+    const CONFIG: &str = "
+modes:
+  resize:
+    bindings:
+      - key: escape
+        modifiers: []
+        action: exit-mode
+";
+
+    /// An executor over one monitor with one window focused, and a keymap with a `resize`
+    /// mode.
+    fn executor() -> (ActionExecutor<VirtualDesktop>, Keymap, WindowId) {
+        let mut desktop = VirtualDesktop::new();
+        desktop.add_monitor(Rect::xyxy(0, 0, 1920, 1080), Rect::xyxy(0, 0, 1920, 1040));
+        let window = desktop.add_window(Rect::xyxy(100, 100, 500, 400));
+        let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+
+        (ActionExecutor::new(desktop), Keymap::new(&config), window)
+    }
+
+    fn execute(action: Action) -> Request {
+        Request::Execute(action)
+    }
+
+    /// Runs `client` on a thread of its own, answering its requests with `handler` until it
+    /// returns.
+    fn answer<T, C>(client: C, mut handler: impl FnMut(Request) -> Response) -> T
+    where
+        T: Send + 'static,
+        C: FnOnce(RequestSender) -> T + Send + 'static,
+    {
+        let (sender, queue) = channel(|| {});
+        let client = std::thread::spawn(move || client(sender));
+
+        while !client.is_finished() {
+            queue.handle_pending(&mut handler);
+            std::thread::yield_now();
+        }
+
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn channel_answers_requests_in_process() {
+        let woken = Arc::new(AtomicUsize::new(0));
+        let (sender, queue) = channel({
+            let woken = woken.clone();
+            move || {
+                woken.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        let client = std::thread::spawn(move || sender.send(execute(Action::Tile)).unwrap());
+        while woken.load(Ordering::SeqCst) == 0 {
+            std::thread::yield_now();
+        }
+
+        let mut handled = Vec::new();
+        queue.handle_pending(|request| {
+            handled.push(request);
+            Response::Mode("answered".to_owned())
+        });
+
+        assert_eq!(
+            client.join().unwrap(),
+            Response::Mode("answered".to_owned())
+        );
+        assert_eq!(handled, vec![execute(Action::Tile)]);
+        assert_eq!(woken.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn requests_fail_once_the_queue_is_gone() {
+        let (sender, queue) = channel(|| {});
+        drop(queue);
+
+        assert!(matches!(
+            sender.send(execute(Action::Tile)),
+            Err(IpcError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn serve_connection_answers_every_line() {
+        let input = concat!(
+            "{\"execute\":\"maximize\"}\n",
+            "not json\n",
+            "{\"execute\":{\"switch-workspace\":2}}\n",
+        );
+
+        let (result, output) = answer(
+            move |sender| {
+                let mut output = Vec::new();
+                let result = serve_connection(Cursor::new(input), &mut output, &sender);
+                (result, output)
+            },
+            |request| Response::Error(format!("{:?}", request)),
+        );
+        assert!(result.is_ok());
+
+        let mut reader = Cursor::new(output);
+        let mut responses = Vec::new();
+        while let Some(response) = read_message::<Response, _>(&mut reader).unwrap() {
+            responses.push(response);
+        }
+
+        assert_eq!(responses.len(), 3);
+        assert_eq!(
+            responses[0],
+            Response::Error(format!("{:?}", execute(Action::Maximize)))
+        );
+        assert!(
+            matches!(&responses[1], Response::Error(message) if message.starts_with("invalid request"))
+        );
+        assert_eq!(
+            responses[2],
+            Response::Error(format!("{:?}", execute(Action::SwitchWorkspace(2))))
+        );
+    }
+
+    #[test]
+    fn mode_changes_go_to_the_keymap() {
+        let (mut executor, mut keymap, _) = executor();
+
+        assert_eq!(
+            handle_request(
+                &mut executor,
+                &mut keymap,
+                execute(Action::EnterMode {
+                    mode: "resize".to_owned()
+                })
+            ),
+            Response::Mode("resize".to_owned())
+        );
+        assert_eq!(keymap.mode(), "resize");
+
+        assert_eq!(
+            handle_request(&mut executor, &mut keymap, execute(Action::ExitMode)),
+            Response::Mode(DEFAULT_MODE.to_owned())
+        );
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn unknown_modes_are_refused() {
+        let (mut executor, mut keymap, _) = executor();
+
+        let response = handle_request(
+            &mut executor,
+            &mut keymap,
+            execute(Action::EnterMode {
+                mode: "move".to_owned(),
+            }),
+        );
+
+        assert_eq!(
+            response,
+            Response::Error("there is no mode named \"move\"".to_owned())
+        );
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn other_actions_go_to_the_executor() {
+        let (mut executor, mut keymap, window) = executor();
+
+        assert_eq!(
+            handle_request(&mut executor, &mut keymap, execute(Action::Maximize)),
+            Response::Executed(ActionOutcome::Moved {
+                window,
+                from: Rect::xyxy(100, 100, 500, 400),
+                to: Rect::xyxy(0, 0, 1920, 1040),
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    fn socket_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("whimsy-test-{}-{}.sock", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn server_answers_over_a_socket() {
+        let endpoint = socket_path("round-trip");
+        let (sender, queue) = channel(|| {});
+        let server = Server::spawn(endpoint.clone(), sender).unwrap();
+
+        let client = std::thread::spawn(move || send_request(&endpoint, &execute(Action::Tile)));
+        while !client.is_finished() {
+            queue.handle_pending(|_| Response::Mode("answered".to_owned()));
+            std::thread::yield_now();
+        }
+
+        assert_eq!(
+            client.join().unwrap().unwrap(),
+            Response::Mode("answered".to_owned())
+        );
+        drop(server);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn server_refuses_a_socket_that_is_in_use() {
+        let endpoint = socket_path("in-use");
+        let (sender, _queue) = channel(|| {});
+        let server = Server::spawn(endpoint.clone(), sender.clone()).unwrap();
+
+        let error = Server::spawn(endpoint.clone(), sender).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+        assert!(endpoint.exists());
+
+        drop(server);
+        assert!(!endpoint.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn server_replaces_a_stale_socket() {
+        let endpoint = socket_path("stale");
+        drop(std::os::unix::net::UnixListener::bind(&endpoint).unwrap());
+        assert!(endpoint.exists());
+
+        let (sender, _queue) = channel(|| {});
+        let server = Server::spawn(endpoint.clone(), sender).unwrap();
+        assert!(std::os::unix::net::UnixStream::connect(&endpoint).is_ok());
+
+        drop(server);
+    }
+}
@@ -1,8 +1,19 @@
-use std::collections::HashSet;
+#[cfg(windows)]
+use std::collections::HashMap;
+use std::fmt::Write;
 
+#[cfg(windows)]
 use winapi::um::winuser;
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+use crate::backend::WindowId;
+use crate::config::Binding;
+#[cfg(windows)]
+use crate::error::Win32Error;
+use crate::error::{Error, Result};
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
 #[serde(rename_all = "kebab-case")]
 pub enum Modifier {
     #[serde(alias = "ctrl")]
@@ -13,6 +24,7 @@ pub enum Modifier {
     Super,
 }
 
+#[cfg(windows)]
 fn modifier_to_flag_code(modifier: &Modifier) -> isize {
     match modifier {
         Modifier::Control => winuser::MOD_CONTROL,
@@ -22,7 +34,7 @@ fn modifier_to_flag_code(modifier: &Modifier) -> isize {
     }
 }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[repr(i32)]
 #[serde(rename_all = "kebab-case")]
 pub enum Key {
@@ -150,28 +162,280 @@ pub enum Key {
     ScrollLock = 0x91,
 }
 
+/// A key pressed together with a set of modifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Chord {
+    pub key: Key,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+}
+
+impl Chord {
+    /// Creates a chord. Modifiers are put in a fixed order, so that chords compare equal
+    /// however their modifiers were written.
+    pub fn new(key: Key, modifiers: &[Modifier]) -> Chord {
+        let mut modifiers = modifiers.to_vec();
+        modifiers.sort();
+        modifiers.dedup();
+        Chord { key, modifiers }
+    }
+}
+
+impl std::fmt::Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format_combo(self.key, &self.modifiers))
+    }
+}
+
+/// The largest ID an application may give a hotkey; `RegisterHotKey` only accepts 0x0000
+/// through 0xBFFF.
+pub const MAXIMUM_HOTKEY_ID: i32 = 0xBFFF;
+
+/// Hands out hotkey IDs. IDs are reused once they are released, since hotkeys come and go
+/// with every mode change and the range of IDs is small.
+#[derive(Debug, Default)]
+pub struct HotkeyIds {
+    next: i32,
+    free: Vec<i32>,
+}
+
+impl HotkeyIds {
+    /// Takes an ID, preferring the lowest released one. Returns `None` if every ID is in
+    /// use.
+    pub fn allocate(&mut self) -> Option<i32> {
+        if let Some(index) = (0..self.free.len()).min_by_key(|&index| self.free[index]) {
+            return Some(self.free.swap_remove(index));
+        }
+
+        if self.next > MAXIMUM_HOTKEY_ID {
+            return None;
+        }
+
+        self.next += 1;
+        Some(self.next - 1)
+    }
+
+    /// Makes an ID available again.
+    pub fn release(&mut self, id: i32) {
+        if (0..self.next).contains(&id) && !self.free.contains(&id) {
+            self.free.push(id);
+        }
+    }
+
+    /// The number of IDs that have ever been handed out at once.
+    pub fn high_water_mark(&self) -> i32 {
+        self.next
+    }
+}
+
+#[cfg(windows)]
+#[derive(Default)]
 pub struct Keybinds {
-    bind_id_increment: i32,
-    active_binds: HashSet<i32>,
+    ids: HotkeyIds,
+    /// The key combination of every registered keybind, by internal ID.
+    active_binds: HashMap<i32, (Key, Vec<Modifier>)>,
+    /// The thread timer that is running, if any.
+    timer: Option<usize>,
 }
 
 pub enum KeybindMessage {
     Quit,
     BindActivated(i32),
+    /// The configuration file has changed on disk.
+    ConfigChanged,
+    /// A top-level window was shown.
+    WindowShown(WindowId),
+    /// A top-level window was hidden or minimized.
+    WindowHidden(WindowId),
+    /// A top-level window was destroyed.
+    WindowDestroyed(WindowId),
+    /// The timer started with `Keybinds::start_timer` has elapsed.
+    TimerElapsed,
+    /// Another process has sent requests, which are waiting in the IPC request queue.
+    RequestReceived,
+}
+
+/// Thread message posted to the message loop when the configuration file changes.
+#[cfg(windows)]
+const WM_WHIMSY_CONFIG_CHANGED: u32 = winuser::WM_APP + 1;
+
+/// Thread message posted to the message loop when a window is shown. The window handle is
+/// in `wParam`.
+#[cfg(windows)]
+const WM_WHIMSY_WINDOW_SHOWN: u32 = winuser::WM_APP + 2;
+
+/// Thread message posted to the message loop when an IPC request is queued.
+#[cfg(windows)]
+const WM_WHIMSY_REQUEST_RECEIVED: u32 = winuser::WM_APP + 3;
+
+/// Thread message posted to the message loop when a window is hidden. The window handle is
+/// in `wParam`.
+#[cfg(windows)]
+const WM_WHIMSY_WINDOW_HIDDEN: u32 = winuser::WM_APP + 4;
+
+/// Thread message posted to the message loop when a window is destroyed. The window handle
+/// is in `wParam`.
+#[cfg(windows)]
+const WM_WHIMSY_WINDOW_DESTROYED: u32 = winuser::WM_APP + 5;
+
+/// A handle to the message loop that can be sent to other threads.
+#[cfg(windows)]
+#[derive(Debug, Copy, Clone)]
+pub struct MessageLoopHandle {
+    thread_id: u32,
 }
 
+#[cfg(windows)]
+impl MessageLoopHandle {
+    /// Asks the message loop to stop, as if the thread had received `WM_QUIT`.
+    pub fn quit(&self) -> Result<()> {
+        self.post(winuser::WM_QUIT, 0)
+    }
+
+    pub fn notify_config_changed(&self) -> Result<()> {
+        self.post(WM_WHIMSY_CONFIG_CHANGED, 0)
+    }
+
+    pub fn notify_window_shown(&self, window: WindowId) -> Result<()> {
+        self.post(WM_WHIMSY_WINDOW_SHOWN, window.0 as usize)
+    }
+
+    pub fn notify_window_hidden(&self, window: WindowId) -> Result<()> {
+        self.post(WM_WHIMSY_WINDOW_HIDDEN, window.0 as usize)
+    }
+
+    pub fn notify_window_destroyed(&self, window: WindowId) -> Result<()> {
+        self.post(WM_WHIMSY_WINDOW_DESTROYED, window.0 as usize)
+    }
+
+    pub fn notify_request_received(&self) -> Result<()> {
+        self.post(WM_WHIMSY_REQUEST_RECEIVED, 0)
+    }
+
+    fn post(&self, message: u32, wparam: usize) -> Result<()> {
+        unsafe {
+            evaluate_fallible_winapi!(winuser::PostThreadMessageW(
+                self.thread_id,
+                message,
+                wparam,
+                0
+            ))
+            .map_err(Error::Win32)
+        }
+    }
+}
+
+/// Formats a key combination the way users write it, e.g. `Super+Shift+Left`.
+pub fn format_combo(key: Key, modifiers: &[Modifier]) -> String {
+    let mut combo = String::new();
+    for modifier in modifiers {
+        write!(combo, "{:?}+", modifier).unwrap();
+    }
+    write!(combo, "{:?}", key).unwrap();
+    combo
+}
+
+/// The outcome of registering every binding in a configuration. Bindings that could not
+/// be registered do not prevent the others from working.
+pub struct BindingRegistration<'a> {
+    /// Every binding, in configuration order, with its internal ID or registration error.
+    pub results: Vec<(&'a Binding, Result<i32>)>,
+}
+
+impl<'a> BindingRegistration<'a> {
+    pub fn registered(&self) -> impl Iterator<Item = (i32, &'a Binding)> + '_ {
+        self.results
+            .iter()
+            .filter_map(|(binding, result)| result.as_ref().ok().map(|&id| (id, *binding)))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (&'a Binding, &Error)> + '_ {
+        self.results
+            .iter()
+            .filter_map(|(binding, result)| result.as_ref().err().map(|error| (*binding, error)))
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.failures().next().is_some()
+    }
+
+    /// A short summary of the bindings that failed, suitable for printing at startup.
+    pub fn summary(&self) -> String {
+        let failure_count = self.failures().count();
+        if failure_count == 0 {
+            return format!("All {} hotkeys were registered.\n", self.results.len());
+        }
+
+        let mut summary = format!(
+            "{} of {} hotkeys could not be registered; the rest are active.\n",
+            failure_count,
+            self.results.len()
+        );
+
+        for (binding, error) in self.failures() {
+            writeln!(
+                summary,
+                "  {}: {}",
+                format_combo(binding.key, &binding.modifiers),
+                describe_failure(error)
+            )
+            .unwrap();
+        }
+
+        summary
+    }
+
+    /// A line for every binding, stating whether it was registered.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+
+        for (binding, result) in &self.results {
+            let status = match result {
+                Ok(_) => String::from("ok"),
+                Err(error) => describe_failure(error),
+            };
+
+            writeln!(
+                report,
+                "{:<24} {:<48} {}",
+                format_combo(binding.key, &binding.modifiers),
+                format!("{:?}", binding.action),
+                status
+            )
+            .unwrap();
+        }
+
+        report
+    }
+}
+
+pub fn describe_failure(error: &Error) -> String {
+    if error.is_hotkey_conflict() {
+        String::from("already registered by another application or by another binding")
+    } else {
+        error.to_string()
+    }
+}
+
+#[cfg(windows)]
 impl Keybinds {
     pub fn new() -> Keybinds {
-        Keybinds {
-            bind_id_increment: 0,
-            active_binds: HashSet::new(),
-        }
+        Keybinds::default()
     }
 
     // FIXME: Use an actual enum instead of an integer code for better error checking.
     // Need to figure out how to autogenerate this.
-    pub fn register_keybind(&mut self, key_code: Key, modifiers: &[Modifier]) -> Result<i32, ()> {
-        let id = self.bind_id_increment;
+    pub fn register_keybind(&mut self, key_code: Key, modifiers: &[Modifier]) -> Result<i32> {
+        let id = self.ids.allocate().ok_or(Error::NoHotkeyIds)?;
+        if let Err(error) = self.register_hotkey(id, key_code, modifiers) {
+            self.ids.release(id);
+            return Err(error);
+        }
+
+        Ok(id)
+    }
+
+    fn register_hotkey(&mut self, id: i32, key_code: Key, modifiers: &[Modifier]) -> Result<()> {
         let modifier_flags = modifiers.iter().fold(0, |accumulator, modifier| {
             accumulator | modifier_to_flag_code(modifier)
         }) | winuser::MOD_NOREPEAT;
@@ -190,53 +454,434 @@ impl Keybinds {
                 // cast is safe, the maximum value of modifier_flags is 0x400F
                 modifier_flags as u32,
                 key_code as u32
-            ));
+            ))
+            .map_err(|error| Error::RegisterHotkey {
+                key: key_code,
+                modifiers: modifiers.to_vec(),
+                error,
+            })?;
         }
 
-        self.active_binds.insert(id);
-        self.bind_id_increment += 1;
-        Ok(id)
+        self.active_binds.insert(id, (key_code, modifiers.to_vec()));
+        Ok(())
+    }
+
+    /// Returns the ID of the keybind registered for a key combination, if there is one.
+    pub fn find_keybind(&self, key_code: Key, modifiers: &[Modifier]) -> Option<i32> {
+        let chord = Chord::new(key_code, modifiers);
+        self.active_binds
+            .iter()
+            .find(|(_, (key, modifiers))| Chord::new(*key, modifiers) == chord)
+            .map(|(&id, _)| id)
+    }
+
+    /// Registers every binding, continuing past any that fail. Bindings that start with the
+    /// same chord, such as key sequences with a shared leader, share a keybind.
+    pub fn register_bindings<'a>(
+        &mut self,
+        bindings: impl IntoIterator<Item = &'a Binding>,
+    ) -> BindingRegistration<'a> {
+        let results = bindings
+            .into_iter()
+            .map(|binding| {
+                if let Some(id) = self.find_keybind(binding.key, &binding.modifiers) {
+                    return (binding, Ok(id));
+                }
+
+                let result = self.register_keybind(binding.key, &binding.modifiers);
+                if let Err(error) = &result {
+                    log::warn!("{}", error);
+                }
+
+                (binding, result)
+            })
+            .collect();
+
+        BindingRegistration { results }
     }
 
-    pub fn poll_message_loop(&self) -> Result<KeybindMessage, ()> {
+    pub fn unregister_keybind(&mut self, id: i32) -> Result<()> {
+        if !self.active_binds.contains_key(&id) {
+            return Err(Error::NoSuchHotkey(id));
+        }
+
+        log::debug!("Unregistering keybind with internal ID {:?}.", id);
+
         unsafe {
-            let mut msg: winuser::MSG = std::mem::zeroed();
+            evaluate_fallible_winapi!(winuser::UnregisterHotKey(std::ptr::null_mut(), id))
+                .map_err(Error::Win32)?;
+        }
+
+        self.active_binds.remove(&id);
+        self.ids.release(id);
+        Ok(())
+    }
+
+    /// Unregisters every active keybind. Keybinds that fail to unregister are logged and
+    /// forgotten; the first error is returned.
+    pub fn unregister_all(&mut self) -> Result<()> {
+        let mut first_error = None;
+        let mut ids: Vec<i32> = self.active_binds.keys().copied().collect();
+        ids.sort_unstable();
+
+        for id in ids {
+            if let Err(error) = self.unregister_keybind(id) {
+                log::error!("Unable to unregister keybind {}: {}", id, error);
+                self.active_binds.remove(&id);
+                self.ids.release(id);
+                first_error.get_or_insert(error);
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Changes the key combination of an existing keybind, keeping its ID. If the new
+    /// combination cannot be registered, the old one is restored.
+    pub fn rebind(&mut self, id: i32, key_code: Key, modifiers: &[Modifier]) -> Result<()> {
+        let (old_key, old_modifiers) = self
+            .active_binds
+            .get(&id)
+            .cloned()
+            .ok_or(Error::NoSuchHotkey(id))?;
+
+        self.unregister_keybind(id)?;
+
+        if let Err(error) = self.register_hotkey(id, key_code, modifiers) {
+            if let Err(restore_error) = self.register_hotkey(id, old_key, &old_modifiers) {
+                log::error!(
+                    "Unable to restore keybind {} after a failed rebind: {}",
+                    id,
+                    restore_error
+                );
+            }
+
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_registered(&self, id: i32) -> bool {
+        self.active_binds.contains_key(&id)
+    }
+
+    /// Starts a timer that elapses once after `duration`, replacing any timer that is
+    /// already running.
+    pub fn start_timer(&mut self, duration: std::time::Duration) -> Result<()> {
+        self.stop_timer();
+
+        // Thread timers ignore the ID passed in and hand back a new one.
+        let timer = unsafe {
+            winuser::SetTimer(
+                std::ptr::null_mut(),
+                0,
+                duration.as_millis().min(u32::MAX as u128) as u32,
+                None,
+            )
+        };
 
-            // Do not use evaluate_fallible_winapi! because GetMessage has different return values than it can accept.
-            let result = winuser::GetMessageW(
+        if timer == 0 {
+            return Err(Error::Win32(Win32Error::last_error("winuser::SetTimer")));
+        }
+
+        self.timer = Some(timer);
+        Ok(())
+    }
+
+    pub fn stop_timer(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            unsafe {
+                winuser::KillTimer(std::ptr::null_mut(), timer);
+            }
+        }
+    }
+
+    /// Returns a handle that other threads can use to post messages to this message loop.
+    /// This must be called from the thread that polls the message loop.
+    pub fn message_loop_handle(&self) -> MessageLoopHandle {
+        unsafe {
+            // Threads do not get a message queue until they call a message function, and
+            // messages posted before then are lost. Peeking forces the queue to exist.
+            let mut msg: winuser::MSG = std::mem::zeroed();
+            winuser::PeekMessageW(
                 &mut msg,
                 std::ptr::null_mut(),
-                winuser::WM_HOTKEY,
-                winuser::WM_HOTKEY,
+                winuser::WM_USER,
+                winuser::WM_USER,
+                winuser::PM_NOREMOVE,
             );
 
-            match result {
-                // Result code 0 is a WM_QUIT message; we should stop the loop here.
-                0 => Ok(KeybindMessage::Quit),
-                // -1 is an error.
-                -1 => {
-                    log::error!(
-                        "Error from GetMessageW: {}",
-                        winapi::um::errhandlingapi::GetLastError(),
-                    );
-
-                    Err(())
+            MessageLoopHandle {
+                thread_id: winapi::um::processthreadsapi::GetCurrentThreadId(),
+            }
+        }
+    }
+
+    pub fn poll_message_loop(&self) -> Result<KeybindMessage> {
+        loop {
+            unsafe {
+                let mut msg: winuser::MSG = std::mem::zeroed();
+
+                // Do not use evaluate_fallible_winapi! because GetMessage has different return values than it can accept.
+                let result = winuser::GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0);
+
+                match result {
+                    // Result code 0 is a WM_QUIT message; we should stop the loop here.
+                    0 => return Ok(KeybindMessage::Quit),
+                    // -1 is an error.
+                    -1 => return Err(Error::Win32(Win32Error::last_error("winuser::GetMessageW"))),
+                    // Anything else is a successful message retrieval; if this is the case, `msg`
+                    // is safe to read.
+                    _ => match msg.message {
+                        winuser::WM_HOTKEY => {
+                            let id = msg.wParam as i32;
+
+                            if !self.active_binds.contains_key(&id) {
+                                return Err(Error::UnregisteredHotkey(id));
+                            }
+
+                            log::trace!("Keybind {} pressed", id);
+                            return Ok(KeybindMessage::BindActivated(id));
+                        }
+                        WM_WHIMSY_CONFIG_CHANGED => return Ok(KeybindMessage::ConfigChanged),
+                        WM_WHIMSY_REQUEST_RECEIVED => return Ok(KeybindMessage::RequestReceived),
+                        // Thread timers fire repeatedly; the caller stops the timer once it
+                        // has handled it.
+                        winuser::WM_TIMER
+                            if msg.hwnd.is_null() && Some(msg.wParam) == self.timer =>
+                        {
+                            return Ok(KeybindMessage::TimerElapsed);
+                        }
+                        WM_WHIMSY_WINDOW_SHOWN => {
+                            let window = WindowId(msg.wParam as isize);
+                            return Ok(KeybindMessage::WindowShown(window));
+                        }
+                        WM_WHIMSY_WINDOW_HIDDEN => {
+                            let window = WindowId(msg.wParam as isize);
+                            return Ok(KeybindMessage::WindowHidden(window));
+                        }
+                        WM_WHIMSY_WINDOW_DESTROYED => {
+                            let window = WindowId(msg.wParam as isize);
+                            return Ok(KeybindMessage::WindowDestroyed(window));
+                        }
+                        // Anything else is not meant for us; let the default handling take it.
+                        _ => {
+                            winuser::TranslateMessage(&msg);
+                            winuser::DispatchMessageW(&msg);
+                        }
+                    },
                 }
-                // Anything else is a successful message retrieval; if this is the case, `msg`
-                // is safe to read.
-                _ => {
-                    debug_assert!(msg.message == winuser::WM_HOTKEY, "The keybind message loop only handles WM_HOTKEY messages, but it has received a message that is not a hotkey message.");
-                    let id = msg.wParam as i32;
-
-                    if !self.active_binds.contains(&id) {
-                        log::error!("Unregistered keybind with ID {} was fired.", id);
-                        return Err(());
-                    }
-
-                    log::trace!("Keybind {} pressed", id);
-                    Ok(KeybindMessage::BindActivated(id))
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Keybinds {
+    fn drop(&mut self) {
+        // Hotkeys are released when the process exits anyway, but a Keybinds may be dropped
+        // long before then.
+        let _ = self.unregister_all();
+        self.stop_timer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::{Action, Config};
+    use crate::error::Win32Error;
+    use crate::keymap::Keymap;
+
+    #[test]
+    fn released_ids_are_reused_lowest_first() {
+        let mut ids = HotkeyIds::default();
+        assert_eq!(
+            (0..4).map(|_| ids.allocate()).collect::<Vec<_>>(),
+            vec![Some(0), Some(1), Some(2), Some(3)]
+        );
+
+        ids.release(2);
+        ids.release(0);
+        // IDs that were never handed out, or are already free, are ignored.
+        ids.release(0);
+        ids.release(7);
+
+        assert_eq!(ids.allocate(), Some(0));
+        assert_eq!(ids.allocate(), Some(2));
+        assert_eq!(ids.allocate(), Some(4));
+        assert_eq!(ids.high_water_mark(), 5);
+    }
+
+    #[test]
+    fn ids_stay_within_the_range_windows_accepts() {
+        let mut ids = HotkeyIds::default();
+        let mut last = None;
+        while let Some(id) = ids.allocate() {
+            last = Some(id);
+        }
+
+        assert_eq!(last, Some(MAXIMUM_HOTKEY_ID));
+        assert_eq!(ids.allocate(), None);
+
+        ids.release(10);
+        assert_eq!(ids.allocate(), Some(10));
+    }
+
+    #[test]
+    fn mode_changes_do_not_grow_the_id_space() {
+        let config: Config = serde_yaml::from_str(
+            "
+bindings:
+  - {key: r, modifiers: [alt], action: {enter-mode: {mode: resize}}}
+  - {key: m, modifiers: [alt], action: tile}
+modes:
+  resize:
+    bindings:
+      - {key: left, modifiers: [], action: undo}
+      - {key: right, modifiers: [], action: redo}
+      - {key: escape, modifiers: [], action: exit-mode}
+",
+        )
+        .unwrap();
+        let mut keymap = Keymap::new(&config);
+        let mut ids = HotkeyIds::default();
+        let mut registered: HashMap<Chord, i32> = HashMap::new();
+
+        // Registers and unregisters hotkeys the way the message loop does on every change.
+        let mut sync = |keymap: &Keymap, ids: &mut HotkeyIds| {
+            let wanted = keymap.active_chords();
+            registered.retain(|chord, id| {
+                let keep = wanted.contains(chord);
+                if !keep {
+                    ids.release(*id);
                 }
+                keep
+            });
+
+            for chord in wanted {
+                registered
+                    .entry(chord)
+                    .or_insert_with(|| ids.allocate().unwrap());
             }
+        };
+
+        for _ in 0..10_000 {
+            sync(&keymap, &mut ids);
+            keymap.enter_mode("resize");
+            sync(&keymap, &mut ids);
+            keymap.exit_mode();
+        }
+
+        // Never more than the three hotkeys of the resize mode at once.
+        assert_eq!(ids.high_water_mark(), 3);
+    }
+
+    fn binding(key: Key, modifiers: &[Modifier], action: Action) -> Binding {
+        Binding {
+            key,
+            modifiers: modifiers.to_vec(),
+            then: Vec::new(),
+            action,
         }
     }
+
+    fn conflict(key: Key, modifiers: &[Modifier]) -> Error {
+        Error::RegisterHotkey {
+            key,
+            modifiers: modifiers.to_vec(),
+            error: Win32Error {
+                api: "winuser::RegisterHotKey",
+                code: 1409,
+                message: "Hot key is already registered.".to_owned(),
+            },
+        }
+    }
+
+    fn bindings() -> Vec<Binding> {
+        vec![
+            binding(
+                Key::Left,
+                &[Modifier::Super, Modifier::Shift],
+                Action::Maximize,
+            ),
+            binding(Key::Right, &[Modifier::Alt], Action::Restore),
+            binding(Key::Up, &[Modifier::Control], Action::Tile),
+        ]
+    }
+
+    #[test]
+    fn summary_when_every_binding_registered() {
+        let bindings = bindings();
+        let registration = BindingRegistration {
+            results: bindings
+                .iter()
+                .zip(0..)
+                .map(|(binding, id)| (binding, Ok(id)))
+                .collect(),
+        };
+
+        assert!(!registration.has_failures());
+        assert_eq!(registration.registered().count(), 3);
+        assert_eq!(registration.summary(), "All 3 hotkeys were registered.\n");
+    }
+
+    #[test]
+    fn summary_lists_the_bindings_that_failed() {
+        let bindings = bindings();
+        let registration = BindingRegistration {
+            results: vec![
+                (
+                    &bindings[0],
+                    Err(conflict(Key::Left, &[Modifier::Super, Modifier::Shift])),
+                ),
+                (&bindings[1], Ok(0)),
+                (&bindings[2], Err(Error::NoHotkeyIds)),
+            ],
+        };
+
+        assert!(registration.has_failures());
+        assert_eq!(
+            registration
+                .registered()
+                .map(|(id, binding)| (id, binding.key))
+                .collect::<Vec<_>>(),
+            vec![(0, Key::Right)]
+        );
+        assert_eq!(
+            registration.summary(),
+            "\
+2 of 3 hotkeys could not be registered; the rest are active.
+  Super+Shift+Left: already registered by another application or by another binding
+  Control+Up: every hotkey ID is in use
+"
+        );
+    }
+
+    #[test]
+    fn report_has_a_line_for_every_binding() {
+        let bindings = bindings();
+        let registration = BindingRegistration {
+            results: vec![
+                (&bindings[0], Ok(0)),
+                (&bindings[1], Err(conflict(Key::Right, &[Modifier::Alt]))),
+                (&bindings[2], Ok(1)),
+            ],
+        };
+
+        assert_eq!(
+            registration.report(),
+            "\
+Super+Shift+Left         Maximize                                         ok
+Alt+Right                Restore                                          already registered by another application or by another binding
+Control+Up               Tile                                             ok
+"
+        );
+    }
 }
@@ -0,0 +1,443 @@
+//! Modes and key sequences. The keymap decides which chords should be registered as hotkeys
+//! at any moment, and what to do when one of them is pressed. Registering the hotkeys and
+//! running actions is left to the caller.
+use std::collections::HashMap;
+
+use crate::config::{Action, Binding, Config};
+use crate::keybind::Chord;
+
+/// The mode made up of the top-level bindings. It is always at the bottom of the mode stack.
+pub const DEFAULT_MODE: &str = "default";
+
+/// What pressing a chord led to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// A binding was completed; its action should be executed.
+    Execute(Action),
+    /// The chord started or continued a key sequence. The next chord should be pressed
+    /// before the sequence timeout elapses.
+    Pending,
+    /// A different mode is now active.
+    ModeChanged,
+    /// The chord does not complete or continue any binding. Any sequence in progress is
+    /// abandoned.
+    Unmatched,
+}
+
+#[derive(Debug, Clone)]
+struct Sequence {
+    chords: Vec<Chord>,
+    action: Action,
+}
+
+impl Sequence {
+    fn from_binding(binding: &Binding) -> Sequence {
+        Sequence {
+            chords: binding.sequence(),
+            action: binding.action.clone(),
+        }
+    }
+}
+
+/// A key sequence that has been partly entered.
+#[derive(Debug, Clone)]
+struct Pending {
+    /// Indices of the sequences in the current mode that start with the chords pressed so
+    /// far.
+    candidates: Vec<usize>,
+    pressed: usize,
+}
+
+#[derive(Debug)]
+pub struct Keymap {
+    modes: HashMap<String, Vec<Sequence>>,
+    /// Active modes, innermost last. [`DEFAULT_MODE`] is always first.
+    stack: Vec<String>,
+    pending: Option<Pending>,
+}
+
+impl Keymap {
+    pub fn new(config: &Config) -> Keymap {
+        let mut keymap = Keymap {
+            modes: HashMap::new(),
+            stack: vec![DEFAULT_MODE.to_owned()],
+            pending: None,
+        };
+
+        keymap.reload(config);
+        keymap
+    }
+
+    /// Picks up new bindings and modes. Modes that no longer exist are left, and any
+    /// sequence in progress is abandoned. The top-level bindings take the place of a mode
+    /// named [`DEFAULT_MODE`], which validation reports.
+    pub fn reload(&mut self, config: &Config) {
+        let sequences =
+            |bindings: &[Binding]| bindings.iter().map(Sequence::from_binding).collect();
+
+        self.modes = config
+            .modes
+            .iter()
+            .map(|(name, mode)| (name.clone(), sequences(&mode.bindings)))
+            .collect();
+        if config.modes.contains_key(DEFAULT_MODE) {
+            log::warn!(
+                "Ignoring the mode named {:?}, which is the mode of the top-level bindings",
+                DEFAULT_MODE
+            );
+        }
+        self.modes
+            .insert(DEFAULT_MODE.to_owned(), sequences(&config.bindings));
+
+        let modes = &self.modes;
+        self.stack.retain(|mode| modes.contains_key(mode));
+        self.pending = None;
+    }
+
+    /// The name of the innermost active mode.
+    pub fn mode(&self) -> &str {
+        self.stack.last().map_or(DEFAULT_MODE, String::as_str)
+    }
+
+    pub fn has_mode(&self, mode: &str) -> bool {
+        self.modes.contains_key(mode)
+    }
+
+    /// Whether a key sequence has been started but not finished.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn sequences(&self) -> &[Sequence] {
+        self.modes.get(self.mode()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the chords that should be registered as hotkeys: the chords that can come
+    /// next in a sequence that has been started, or else the first chord of every binding
+    /// in the current mode. Each chord appears once, in binding order.
+    pub fn active_chords(&self) -> Vec<Chord> {
+        let sequences = self.sequences();
+        let (candidates, pressed): (Vec<usize>, usize) = match &self.pending {
+            Some(pending) => (pending.candidates.clone(), pending.pressed),
+            None => ((0..sequences.len()).collect(), 0),
+        };
+
+        let mut chords: Vec<Chord> = Vec::new();
+        for index in candidates {
+            if let Some(chord) = sequences[index].chords.get(pressed) {
+                if !chords.contains(chord) {
+                    chords.push(chord.clone());
+                }
+            }
+        }
+
+        chords
+    }
+
+    /// Handles a chord being pressed. A binding that is completed by the chord wins over
+    /// longer sequences that start the same way.
+    pub fn press(&mut self, chord: &Chord) -> Step {
+        let pending = self.pending.take();
+        let sequences = self.sequences();
+        let (candidates, pressed) = match pending {
+            Some(pending) => (pending.candidates, pending.pressed),
+            None => ((0..sequences.len()).collect(), 0),
+        };
+
+        let matching: Vec<usize> = candidates
+            .into_iter()
+            .filter(|&index| sequences[index].chords.get(pressed) == Some(chord))
+            .collect();
+        let pressed = pressed + 1;
+
+        if matching.is_empty() {
+            return Step::Unmatched;
+        }
+
+        let completed = matching
+            .iter()
+            .find(|&&index| sequences[index].chords.len() == pressed)
+            .map(|&index| sequences[index].action.clone());
+
+        match completed {
+            Some(action) => self.run(action),
+            None => {
+                self.pending = Some(Pending {
+                    candidates: matching,
+                    pressed,
+                });
+                Step::Pending
+            }
+        }
+    }
+
+    /// Abandons a sequence in progress, as happens when the sequence timeout elapses.
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    fn run(&mut self, action: Action) -> Step {
+        let changed = match &action {
+            Action::EnterMode { mode } => self.enter_mode(mode),
+            Action::ExitMode => self.exit_mode(),
+            _ => return Step::Execute(action),
+        };
+
+        if changed {
+            Step::ModeChanged
+        } else {
+            Step::Unmatched
+        }
+    }
+
+    /// Makes a mode the innermost active mode. Entering [`DEFAULT_MODE`] leaves every other
+    /// mode. Returns whether the active mode changed.
+    pub fn enter_mode(&mut self, mode: &str) -> bool {
+        self.pending = None;
+
+        if mode == DEFAULT_MODE {
+            let changed = self.stack.len() > 1;
+            self.stack.truncate(1);
+            return changed;
+        }
+
+        if !self.modes.contains_key(mode) {
+            log::warn!("There is no mode named {:?}", mode);
+            return false;
+        }
+
+        if self.mode() == mode {
+            return false;
+        }
+
+        self.stack.push(mode.to_owned());
+        true
+    }
+
+    /// Leaves the innermost mode, returning to the one that was active before it. Returns
+    /// whether the active mode changed.
+    pub fn exit_mode(&mut self) -> bool {
+        self.pending = None;
+
+        if self.stack.len() > 1 {
+            self.stack.pop();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keybind::{Key, Modifier};
+
+    const CONFIG: &str = "
+bindings:
+  - key: space
+    modifiers: [alt]
+    then: [{key: w}]
+    action: maximize
+  - key: space
+    modifiers: [alt]
+    then: [{key: r}]
+    action: {enter-mode: {mode: resize}}
+  - key: m
+    modifiers: [alt]
+    action: tile
+modes:
+  resize:
+    bindings:
+      - key: left
+        modifiers: []
+        action: {nudge: {direction: left, distance: {absolute: 10}}}
+      - key: m
+        modifiers: [alt]
+        action: {enter-mode: {mode: move}}
+      - key: escape
+        modifiers: []
+        action: exit-mode
+  move:
+    bindings:
+      - key: escape
+        modifiers: []
+        action: exit-mode
+";
+
+    fn config(source: &str) -> Config {
+        serde_yaml::from_str(source).unwrap()
+    }
+
+    fn alt(key: Key) -> Chord {
+        Chord::new(key, &[Modifier::Alt])
+    }
+
+    fn plain(key: Key) -> Chord {
+        Chord::new(key, &[])
+    }
+
+    #[test]
+    fn leader_then_follow_up_runs_the_binding() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+        assert_eq!(keymap.active_chords(), vec![alt(Key::Space), alt(Key::M)]);
+
+        assert_eq!(keymap.press(&alt(Key::Space)), Step::Pending);
+        assert!(keymap.is_pending());
+        assert_eq!(keymap.active_chords(), vec![plain(Key::W), plain(Key::R)]);
+
+        assert_eq!(
+            keymap.press(&plain(Key::W)),
+            Step::Execute(Action::Maximize)
+        );
+        assert!(!keymap.is_pending());
+        assert_eq!(keymap.active_chords(), vec![alt(Key::Space), alt(Key::M)]);
+    }
+
+    #[test]
+    fn single_chord_bindings_run_at_once() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+
+        assert_eq!(keymap.press(&alt(Key::M)), Step::Execute(Action::Tile));
+        assert!(!keymap.is_pending());
+    }
+
+    #[test]
+    fn a_wrong_follow_up_abandons_the_sequence() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+
+        keymap.press(&alt(Key::Space));
+        assert_eq!(keymap.press(&plain(Key::X)), Step::Unmatched);
+        assert!(!keymap.is_pending());
+        assert_eq!(keymap.press(&alt(Key::M)), Step::Execute(Action::Tile));
+    }
+
+    #[test]
+    fn cancel_abandons_the_sequence() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+
+        keymap.press(&alt(Key::Space));
+        keymap.cancel();
+
+        assert!(!keymap.is_pending());
+        assert_eq!(keymap.active_chords(), vec![alt(Key::Space), alt(Key::M)]);
+        // The follow-up on its own does nothing once the sequence has timed out.
+        assert_eq!(keymap.press(&plain(Key::W)), Step::Unmatched);
+    }
+
+    #[test]
+    fn shorter_bindings_win_over_longer_sequences() {
+        let mut keymap = Keymap::new(&config(
+            "
+bindings:
+  - key: space
+    modifiers: [alt]
+    action: tile
+  - key: space
+    modifiers: [alt]
+    then: [{key: w}]
+    action: maximize
+",
+        ));
+
+        assert_eq!(keymap.press(&alt(Key::Space)), Step::Execute(Action::Tile));
+        assert!(!keymap.is_pending());
+    }
+
+    #[test]
+    fn modes_replace_the_bindings_until_exited() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+
+        keymap.press(&alt(Key::Space));
+        assert_eq!(keymap.press(&plain(Key::R)), Step::ModeChanged);
+        assert_eq!(keymap.mode(), "resize");
+        assert_eq!(
+            keymap.active_chords(),
+            vec![plain(Key::Left), alt(Key::M), plain(Key::Escape)]
+        );
+        // The default bindings are not active in the mode.
+        assert_eq!(keymap.press(&alt(Key::Space)), Step::Unmatched);
+
+        assert_eq!(keymap.press(&plain(Key::Escape)), Step::ModeChanged);
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+        assert_eq!(keymap.press(&plain(Key::Escape)), Step::Unmatched);
+    }
+
+    #[test]
+    fn enter_and_exit_mode_stack() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+
+        assert!(keymap.enter_mode("resize"));
+        assert!(!keymap.enter_mode("resize"));
+        assert!(keymap.enter_mode("move"));
+        assert_eq!(keymap.mode(), "move");
+
+        assert!(keymap.exit_mode());
+        assert_eq!(keymap.mode(), "resize");
+        assert!(keymap.exit_mode());
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+        assert!(!keymap.exit_mode());
+    }
+
+    #[test]
+    fn entering_the_default_mode_leaves_every_mode() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+        keymap.enter_mode("resize");
+        keymap.enter_mode("move");
+
+        assert!(keymap.enter_mode(DEFAULT_MODE));
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+        assert!(!keymap.enter_mode(DEFAULT_MODE));
+    }
+
+    #[test]
+    fn unknown_modes_cannot_be_entered() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+
+        assert!(!keymap.has_mode("missing"));
+        assert!(!keymap.enter_mode("missing"));
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn reload_drops_modes_that_no_longer_exist() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+        keymap.enter_mode("resize");
+        keymap.enter_mode("move");
+
+        let mut without_move = config(CONFIG);
+        without_move.modes.remove("move");
+        keymap.reload(&without_move);
+        assert_eq!(keymap.mode(), "resize");
+        assert!(!keymap.has_mode("move"));
+
+        keymap.reload(&config("bindings: []"));
+        assert_eq!(keymap.mode(), DEFAULT_MODE);
+        assert!(keymap.active_chords().is_empty());
+    }
+
+    #[test]
+    fn reload_abandons_the_sequence() {
+        let mut keymap = Keymap::new(&config(CONFIG));
+        keymap.press(&alt(Key::Space));
+
+        keymap.reload(&config(CONFIG));
+        assert!(!keymap.is_pending());
+    }
+
+    #[test]
+    fn top_level_bindings_take_the_place_of_a_default_mode() {
+        let keymap = Keymap::new(&config(
+            "
+bindings:
+  - {key: m, modifiers: [alt], action: tile}
+modes:
+  default:
+    bindings:
+      - {key: left, modifiers: [], action: undo}
+",
+        ));
+
+        assert_eq!(keymap.active_chords(), vec![alt(Key::M)]);
+    }
+}
@@ -0,0 +1,327 @@
+//! Tiling layouts, which arrange a set of windows so that together they fill an area. The
+//! layouts only do the arithmetic; applying the result to real windows is up to the
+//! [`crate::action::ActionExecutor`].
+use crate::backend::WindowId;
+use crate::config::TilingLayout;
+use crate::window::Rect;
+
+pub trait Layout {
+    /// Computes a rect for each of `count` windows inside `area`. The first rect is for the
+    /// first window in tiling order, and so on.
+    fn rects(&self, area: &Rect, count: usize) -> Vec<Rect>;
+
+    /// Pairs each window with the rect this layout gives it.
+    fn arrange(&self, area: &Rect, windows: &[WindowId]) -> Vec<(WindowId, Rect)> {
+        windows
+            .iter()
+            .copied()
+            .zip(self.rects(area, windows.len()))
+            .collect()
+    }
+}
+
+/// One or more master windows on the left, with the rest stacked on the right.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MasterStack {
+    /// The fraction of the area's width taken up by the master windows.
+    pub ratio: f32,
+    /// How many windows are stacked in the master area.
+    pub masters: usize,
+}
+
+impl Layout for MasterStack {
+    fn rects(&self, area: &Rect, count: usize) -> Vec<Rect> {
+        let masters = self.masters.min(count);
+        if masters == 0 || masters == count {
+            return Rows.rects(area, count);
+        }
+
+        let (master_area, stack_area) = split(area, Axis::Horizontal, self.ratio);
+        let mut rects = Rows.rects(&master_area, masters);
+        rects.extend(Rows.rects(&stack_area, count - masters));
+        rects
+    }
+}
+
+/// Equal-width columns, side by side.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Columns;
+
+impl Layout for Columns {
+    fn rects(&self, area: &Rect, count: usize) -> Vec<Rect> {
+        let columns = count as u32;
+        (0..columns)
+            .map(|column| area.grid_span(columns, 1, column, 0, 1, 1))
+            .collect()
+    }
+}
+
+/// Equal-height rows, one above the other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rows;
+
+impl Layout for Rows {
+    fn rects(&self, area: &Rect, count: usize) -> Vec<Rect> {
+        let rows = count as u32;
+        (0..rows)
+            .map(|row| area.grid_span(1, rows, 0, row, 1, 1))
+            .collect()
+    }
+}
+
+/// Every window fills the whole area.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Monocle;
+
+impl Layout for Monocle {
+    fn rects(&self, area: &Rect, count: usize) -> Vec<Rect> {
+        vec![*area; count]
+    }
+}
+
+/// Binary space partitioning: each window takes part of the space left over by the windows
+/// before it, and the last window takes whatever remains. Space is split along its longer
+/// side, so the layout suits both landscape and portrait monitors.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bsp {
+    /// The fraction of the remaining space that each window takes.
+    pub ratio: f32,
+}
+
+impl Layout for Bsp {
+    fn rects(&self, area: &Rect, count: usize) -> Vec<Rect> {
+        let mut rects = Vec::with_capacity(count);
+        let mut remaining = *area;
+
+        for index in 0..count {
+            if index + 1 == count {
+                rects.push(remaining);
+                break;
+            }
+
+            let (width, height) = remaining.wh();
+            let axis = if height > width {
+                Axis::Vertical
+            } else {
+                Axis::Horizontal
+            };
+
+            let (taken, rest) = split(&remaining, axis, self.ratio);
+            rects.push(taken);
+            remaining = rest;
+        }
+
+        rects
+    }
+}
+
+impl TilingLayout {
+    /// Builds the layout that this configuration describes.
+    pub fn build(&self) -> Box<dyn Layout> {
+        match *self {
+            TilingLayout::MasterStack { ratio, masters } => {
+                Box::new(MasterStack { ratio, masters })
+            }
+            TilingLayout::Columns => Box::new(Columns),
+            TilingLayout::Rows => Box::new(Rows),
+            TilingLayout::Monocle => Box::new(Monocle),
+            TilingLayout::Bsp { ratio } => Box::new(Bsp { ratio }),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Axis {
+    /// Split into a left and a right part.
+    Horizontal,
+    /// Split into a top and a bottom part.
+    Vertical,
+}
+
+/// Splits a rect in two along an axis. The first part takes `ratio` of the rect, clamped so
+/// that neither part is inside out.
+fn split(area: &Rect, axis: Axis, ratio: f32) -> (Rect, Rect) {
+    let ratio = ratio.clamp(0.0, 1.0);
+    let (width, height) = area.wh();
+
+    match axis {
+        Axis::Horizontal => {
+            let edge = area.left + (width as f32 * ratio).round() as i32;
+            (
+                Rect::xyxy(area.left, area.top, edge, area.bottom),
+                Rect::xyxy(edge, area.top, area.right, area.bottom),
+            )
+        }
+        Axis::Vertical => {
+            let edge = area.top + (height as f32 * ratio).round() as i32;
+            (
+                Rect::xyxy(area.left, area.top, area.right, edge),
+                Rect::xyxy(area.left, edge, area.right, area.bottom),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1200,
+        bottom: 900,
+    };
+
+    #[test]
+    fn master_stack_puts_masters_on_the_left() {
+        let layout = MasterStack {
+            ratio: 0.5,
+            masters: 1,
+        };
+
+        assert_eq!(
+            layout.rects(&AREA, 3),
+            vec![
+                Rect::xyxy(0, 0, 600, 900),
+                Rect::xyxy(600, 0, 1200, 450),
+                Rect::xyxy(600, 450, 1200, 900),
+            ]
+        );
+    }
+
+    #[test]
+    fn master_stack_with_several_masters() {
+        let layout = MasterStack {
+            ratio: 0.75,
+            masters: 2,
+        };
+
+        assert_eq!(
+            layout.rects(&AREA, 3),
+            vec![
+                Rect::xyxy(0, 0, 900, 450),
+                Rect::xyxy(0, 450, 900, 900),
+                Rect::xyxy(900, 0, 1200, 900),
+            ]
+        );
+    }
+
+    #[test]
+    fn master_stack_without_a_stack_uses_the_whole_width() {
+        let layout = MasterStack {
+            ratio: 0.5,
+            masters: 2,
+        };
+
+        assert_eq!(layout.rects(&AREA, 1), vec![AREA]);
+        assert_eq!(
+            layout.rects(&AREA, 2),
+            vec![Rect::xyxy(0, 0, 1200, 450), Rect::xyxy(0, 450, 1200, 900)]
+        );
+        assert_eq!(
+            MasterStack {
+                ratio: 0.5,
+                masters: 0,
+            }
+            .rects(&AREA, 2),
+            layout.rects(&AREA, 2)
+        );
+    }
+
+    #[test]
+    fn columns_and_rows_divide_evenly() {
+        assert_eq!(
+            Columns.rects(&AREA, 3),
+            vec![
+                Rect::xyxy(0, 0, 400, 900),
+                Rect::xyxy(400, 0, 800, 900),
+                Rect::xyxy(800, 0, 1200, 900),
+            ]
+        );
+        assert_eq!(
+            Rows.rects(&AREA, 3),
+            vec![
+                Rect::xyxy(0, 0, 1200, 300),
+                Rect::xyxy(0, 300, 1200, 600),
+                Rect::xyxy(0, 600, 1200, 900),
+            ]
+        );
+    }
+
+    #[test]
+    fn monocle_gives_every_window_the_whole_area() {
+        assert_eq!(Monocle.rects(&AREA, 3), vec![AREA; 3]);
+    }
+
+    #[test]
+    fn bsp_splits_the_longer_side() {
+        let layout = Bsp { ratio: 0.5 };
+
+        assert_eq!(
+            layout.rects(&AREA, 4),
+            vec![
+                // 1200x900 is wider than it is tall, then 600x900 is taller than it is wide.
+                Rect::xyxy(0, 0, 600, 900),
+                Rect::xyxy(600, 0, 1200, 450),
+                Rect::xyxy(600, 450, 900, 900),
+                Rect::xyxy(900, 450, 1200, 900),
+            ]
+        );
+    }
+
+    #[test]
+    fn layouts_fill_the_area_without_overlapping() {
+        let layouts: Vec<Box<dyn Layout>> = vec![
+            Box::new(MasterStack {
+                ratio: 0.6,
+                masters: 1,
+            }),
+            Box::new(Columns),
+            Box::new(Rows),
+            Box::new(Bsp { ratio: 0.6 }),
+        ];
+
+        for layout in &layouts {
+            for count in 1..8 {
+                let rects = layout.rects(&AREA, count);
+                assert_eq!(rects.len(), count);
+
+                let area: i64 = rects.iter().map(Rect::area).sum();
+                assert_eq!(area, AREA.area(), "{} windows", count);
+
+                for (index, rect) in rects.iter().enumerate() {
+                    for other in &rects[index + 1..] {
+                        assert_eq!(rect.intersection(other), None);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_windows_get_no_rects() {
+        assert!(Columns.rects(&AREA, 0).is_empty());
+        assert!(Rows.rects(&AREA, 0).is_empty());
+        assert!(Monocle.rects(&AREA, 0).is_empty());
+        assert!(Bsp { ratio: 0.5 }.rects(&AREA, 0).is_empty());
+        assert!(MasterStack {
+            ratio: 0.5,
+            masters: 1
+        }
+        .rects(&AREA, 0)
+        .is_empty());
+    }
+
+    #[test]
+    fn split_clamps_the_ratio() {
+        assert_eq!(
+            split(&AREA, Axis::Horizontal, 1.5),
+            (AREA, Rect::xyxy(1200, 0, 1200, 900))
+        );
+        assert_eq!(
+            split(&AREA, Axis::Vertical, -1.0),
+            (Rect::xyxy(0, 0, 1200, 0), AREA)
+        );
+    }
+}
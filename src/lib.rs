@@ -0,0 +1,23 @@
+// util before all others due to the macros it contains
+#[cfg(windows)]
+mod util;
+
+pub mod action;
+pub mod backend;
+pub mod cli;
+pub mod config;
+pub mod error;
+#[cfg(windows)]
+pub mod events;
+pub mod history;
+pub mod ignore;
+pub mod ipc;
+pub mod keybind;
+pub mod keymap;
+pub mod layout;
+pub mod rules;
+pub mod snapshot;
+pub mod validate;
+pub mod watcher;
+pub mod window;
+pub mod workspace;
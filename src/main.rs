@@ -1,25 +1,43 @@
-// util before all others due to the macros it contains
-mod util;
+#[cfg(windows)]
+use std::collections::{HashMap, HashSet};
 
-mod cli;
-mod config;
-mod keybind;
-mod window;
-
-use std::collections::HashMap;
+#[cfg(windows)]
+use std::sync::{Condvar, Mutex, OnceLock, PoisonError};
+#[cfg(windows)]
+use std::time::Duration;
 
+#[cfg(windows)]
 use color_eyre::eyre::Result;
 
+#[cfg(windows)]
 use structopt::StructOpt;
 
+#[cfg(windows)]
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
+#[cfg(windows)]
 use winapi::shared::winerror;
-use winapi::um::shellapi;
+#[cfg(windows)]
 use winapi::um::winuser;
+#[cfg(windows)]
+use winapi::um::{consoleapi, shellapi, wincon};
+
+#[cfg(windows)]
+use whimsy::{
+    action, backend, cli, config, error, events, ipc, keybind, keymap, validate, watcher, window,
+};
 
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("whimsy manages Win32 windows and can only run on Windows.");
+    std::process::exit(1);
+}
+
+#[cfg(windows)]
 fn main() -> Result<()> {
     color_eyre::install()?;
     dotenv::dotenv()?;
     pretty_env_logger::init();
+    window::enable_dpi_awareness();
 
     let cli_options = cli::CliOptions::from_args();
     match cli_options.command {
@@ -84,83 +102,535 @@ fn main() -> Result<()> {
 
             return Ok(());
         }
+        Some(cli::CliCommand::CheckBindings) => {
+            let config =
+                config::read_config_from_file(&cli_options.config_file)?.unwrap_or_default();
+            let mut kb = keybind::Keybinds::new();
+            // Only the first chord of each binding is a hotkey until it is pressed, and
+            // bindings in modes are registered while their mode is active.
+            let bindings = config
+                .bindings
+                .iter()
+                .chain(config.modes.values().flat_map(|mode| &mode.bindings));
+            let registration = kb.register_bindings(bindings);
+            print!("{}", registration.report());
+
+            if registration.has_failures() {
+                std::process::exit(1);
+            }
+
+            return Ok(());
+        }
+        Some(cli::CliCommand::Message { action }) => {
+            let action: config::Action = action.join(" ").parse()?;
+            let response =
+                ipc::send_request(&ipc::default_endpoint(), &ipc::Request::Execute(action))?;
+            return print_response(&response);
+        }
+        Some(cli::CliCommand::Run { action }) => {
+            let action: config::Action = action.join(" ").parse()?;
+            if needs_running_instance(&action) {
+                println!(
+                    "{:?} only makes sense while whimsy is running; send it with `whimsy msg` instead.",
+                    action
+                );
+                std::process::exit(1);
+            }
+
+            let config =
+                config::read_config_from_file(&cli_options.config_file)?.unwrap_or_default();
+            let mut executor = action::ActionExecutor::new(backend::Win32Backend);
+            executor.apply_config(&config);
+
+            let response = match executor.execute(&action) {
+                Ok(outcome) => ipc::Response::Executed(outcome),
+                Err(error) => ipc::Response::Error(error.to_string()),
+            };
+            return print_response(&response);
+        }
+        Some(cli::CliCommand::Validate { path }) => {
+            let path = path.unwrap_or(cli_options.config_file);
+            let loaded = match config::load_config(&path) {
+                Ok(Some(loaded)) => loaded,
+                Ok(None) => {
+                    println!("{} does not exist", path.display());
+                    std::process::exit(1);
+                }
+                Err(error) => {
+                    // A file that does not deserialize is checked on its own, to point out
+                    // where the problem is.
+                    let diagnostics = match &error {
+                        config::ConfigReadError::DeserializeError { path, .. } => {
+                            let source = std::fs::read_to_string(path)?;
+                            validate::validate(&source)
+                                .iter()
+                                .map(|diagnostic| diagnostic.render(path, &source))
+                                .collect()
+                        }
+                        _ => Vec::new(),
+                    };
+
+                    if diagnostics.is_empty() {
+                        println!("error: {}", error);
+                    }
+                    for diagnostic in diagnostics {
+                        println!("{}", diagnostic);
+                    }
+
+                    std::process::exit(1);
+                }
+            };
+
+            let mut errors = 0;
+            let mut warnings = 0;
+            for file in &loaded.files {
+                let source = std::fs::read_to_string(file)?;
+                for diagnostic in validate::validate_part(&source, &loaded.config) {
+                    match diagnostic.severity {
+                        validate::Severity::Error => errors += 1,
+                        validate::Severity::Warning => warnings += 1,
+                    }
+
+                    println!("{}", diagnostic.render(file, &source));
+                }
+            }
+
+            println!(
+                "{}: {} errors, {} warnings",
+                path.display(),
+                errors,
+                warnings
+            );
+
+            if errors > 0 {
+                std::process::exit(1);
+            }
+
+            return Ok(());
+        }
         None => {}
     }
 
     let config_path = cli_options.config_file;
 
-    let config = config::read_config_from_file(&config_path)?.unwrap_or_default();
+    let (config, mut config_files) = match config::load_config(&config_path)? {
+        Some(loaded) => (loaded.config, loaded.files),
+        None => (config::Config::default(), vec![config_path.clone()]),
+    };
+    let mut executor = ExecutorGuard(action::ActionExecutor::new(backend::Win32Backend));
+    executor.apply_config(&config);
+    tile_if_enabled(&mut executor);
     let mut kb = keybind::Keybinds::new();
-    let mut kb_bindings = HashMap::new();
+    install_console_handler(kb.message_loop_handle());
+    let mut keymap = keymap::Keymap::new(&config);
+    let mut hotkeys = Hotkeys::default();
+    hotkeys.register(&mut kb, &config, &keymap);
+    let mut sequence_timeout =
+        std::time::Duration::from_millis(config.directives.sequence_timeout_ms);
 
-    for binding in &config.bindings {
-        let binding_id = kb
-            .register_keybind(binding.key, &binding.modifiers)
-            .unwrap();
-        kb_bindings.insert(binding_id, binding);
-    }
+    // Rules and automatic tiling need to know when windows come and go, but hotkeys work
+    // without it.
+    let window_events = match events::WindowEventHook::install(kb.message_loop_handle()) {
+        Ok(hook) => Some(hook),
+        Err(error) => {
+            log::error!("Unable to watch for new windows: {}", error);
+            None
+        }
+    };
+
+    let message_loop = kb.message_loop_handle();
+    let (ipc_requests, ipc_queue) = ipc::channel(move || {
+        if let Err(error) = message_loop.notify_request_received() {
+            log::error!("Unable to notify whimsy of an IPC request: {}", error);
+        }
+    });
+
+    // Like window events, IPC is optional; hotkeys still work without it.
+    let ipc_server = match ipc::Server::spawn(ipc::default_endpoint(), ipc_requests) {
+        Ok(server) => Some(server),
+        Err(error) => {
+            log::error!(
+                "Unable to listen for IPC requests at {}: {}",
+                ipc::default_endpoint().display(),
+                error
+            );
+            None
+        }
+    };
+
+    let mut config_watchers = if config.directives.live_reload_configuration {
+        watch_config_files(&config_files, kb.message_loop_handle())
+    } else {
+        Vec::new()
+    };
 
     loop {
-        match kb.poll_message_loop().unwrap() {
+        let message = match kb.poll_message_loop() {
+            Ok(message) => message,
+            // A stray hotkey is not fatal; the rest of the bindings still work.
+            Err(error @ error::Error::UnregisteredHotkey(_)) => {
+                log::error!("{}", error);
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        match message {
             keybind::KeybindMessage::Quit => {
                 log::debug!("Stopping keybind message polling due to a quit message");
                 break;
             }
             keybind::KeybindMessage::BindActivated(id) => {
-                let &binding = kb_bindings.get(&id).unwrap();
-
-                match binding.action {
-                    config::Action::Push {
-                        direction,
-                        fraction,
-                    } => {
-                        if let Some(mut active_window) = window::get_focused_window() {
-                            let monitor = active_window.get_monitor();
-                            let monitor_work_area = monitor.get_work_area().unwrap();
-                            let pushed_rect = monitor_work_area.slice_rect(direction, fraction); 
-                            log::debug!(
-                                "Pushed active window (direction {:?}, fraction {:?}) to rect {:?}",
-                                direction,
-                                fraction,
-                                pushed_rect
-                            );
-                            active_window.set_rect(pushed_rect).unwrap();
+                let chord = hotkeys.chords.get(&id).unwrap().clone();
+
+                match keymap.press(&chord) {
+                    keymap::Step::Execute(action) => {
+                        kb.stop_timer();
+                        if let Err(error) = executor.execute(&action) {
+                            log::error!("Unable to execute action {:?}: {}", action, error);
                         }
                     }
-                    config::Action::Nudge {
-                        direction,
-                        distance,
-                    } => {
-                        if let Some(mut active_window) = window::get_focused_window() {
-                            let starting_rect = active_window.get_rect().unwrap();
-                            let (width, height) = starting_rect.wh();
-                            let absolute_distance = match distance {
-                                config::Metric::Absolute(value) => value,
-                                config::Metric::Percent(fraction) => match direction {
-                                    config::Direction::Up | config::Direction::Down => {
-                                        height as f32 * fraction
-                                    }
-                                    config::Direction::Left | config::Direction::Right => {
-                                        width as f32 * fraction
-                                    }
-                                },
-                            } as i32;
-
-                            let nudged_rect = starting_rect.nudge(direction, absolute_distance);
-
-                            active_window.set_rect(nudged_rect).unwrap();
-                            log::debug!(
-                                "Nudged active window {:?}px in direction {:?} - new rect {:?}",
-                                absolute_distance,
-                                direction,
-                                nudged_rect
-                            );
+                    keymap::Step::Pending => {
+                        if let Err(error) = kb.start_timer(sequence_timeout) {
+                            log::error!("Unable to time out the key sequence: {}", error);
                         }
                     }
+                    keymap::Step::ModeChanged => {
+                        kb.stop_timer();
+                        log::info!("Entered mode {}", keymap.mode());
+                    }
+                    keymap::Step::Unmatched => {
+                        kb.stop_timer();
+                        log::debug!("{} does not continue any key sequence", chord);
+                    }
                 }
+
+                hotkeys.sync(&mut kb, &keymap);
+            }
+            keybind::KeybindMessage::TimerElapsed => {
+                log::debug!("Key sequence timed out");
+                kb.stop_timer();
+                keymap.cancel();
+                hotkeys.sync(&mut kb, &keymap);
+            }
+            keybind::KeybindMessage::RequestReceived => {
+                ipc_queue.handle_pending(|request| {
+                    ipc::handle_request(&mut executor, &mut keymap, request)
+                });
+                // Requests can change the mode.
+                hotkeys.sync(&mut kb, &keymap);
+            }
+            keybind::KeybindMessage::WindowShown(window) => {
+                // Windows often disappear again before they can be looked at, so failures
+                // here are expected.
+                if let Err(error) = executor.window_shown(window) {
+                    log::debug!(
+                        "Unable to handle window {:?} being shown: {}",
+                        window,
+                        error
+                    );
+                }
+            }
+            keybind::KeybindMessage::WindowHidden(window) => {
+                if let Err(error) = executor.window_hidden(window) {
+                    log::debug!(
+                        "Unable to handle window {:?} being hidden: {}",
+                        window,
+                        error
+                    );
+                }
+            }
+            keybind::KeybindMessage::WindowDestroyed(window) => {
+                if let Err(error) = executor.window_destroyed(window) {
+                    log::debug!(
+                        "Unable to handle window {:?} being destroyed: {}",
+                        window,
+                        error
+                    );
+                }
+            }
+            keybind::KeybindMessage::ConfigChanged => {
+                let (new_config, new_files) = match config::load_config(&config_path) {
+                    Ok(Some(loaded)) => (loaded.config, loaded.files),
+                    Ok(None) => (config::Config::default(), vec![config_path.clone()]),
+                    Err(error) => {
+                        println!(
+                            "Unable to reload the configuration file at {}; the previous configuration is still active.\nError: {}",
+                            config_path.display(),
+                            error
+                        );
+                        continue;
+                    }
+                };
+
+                kb.stop_timer();
+                keymap.reload(&new_config);
+                hotkeys.register(&mut kb, &new_config, &keymap);
+                sequence_timeout =
+                    std::time::Duration::from_millis(new_config.directives.sequence_timeout_ms);
+                executor.apply_config(&new_config);
+                tile_if_enabled(&mut executor);
+
+                if !new_config.directives.live_reload_configuration {
+                    log::info!("Live reload was disabled by the new configuration; no longer watching the configuration file");
+                    config_watchers.clear();
+                } else if new_files != config_files {
+                    // Includes were added or removed.
+                    config_watchers = watch_config_files(&new_files, kb.message_loop_handle());
+                }
+                config_files = new_files;
+
+                log::info!(
+                    "Reloaded configuration from {} ({} hotkeys active in mode {})",
+                    config_path.display(),
+                    hotkeys.chords.len(),
+                    keymap.mode()
+                );
             }
         }
     }
 
+    // Windows on other workspaces are shown again before anything else is torn down.
+    drop(executor);
+    drop(window_events);
+    drop(ipc_server);
+    drop(config_watchers);
     Ok(())
 }
+
+/// Set once the windows hidden on workspaces have been shown again, for the console control
+/// handler to wait on.
+#[cfg(windows)]
+static WORKSPACES_SHOWN: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+/// Where the console control handler asks whimsy to stop.
+#[cfg(windows)]
+static CONSOLE_TARGET: OnceLock<keybind::MessageLoopHandle> = OnceLock::new();
+
+/// Owns the executor of the running whimsy. Windows on workspaces that are not shown are
+/// hidden, and would stay hidden once whimsy is gone, so they are shown again when this is
+/// dropped: when the message loop stops, when it fails and when whimsy panics.
+#[cfg(windows)]
+struct ExecutorGuard(action::ActionExecutor<backend::Win32Backend>);
+
+#[cfg(windows)]
+impl std::ops::Deref for ExecutorGuard {
+    type Target = action::ActionExecutor<backend::Win32Backend>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(windows)]
+impl std::ops::DerefMut for ExecutorGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ExecutorGuard {
+    fn drop(&mut self) {
+        self.0.show_all_workspaces();
+
+        let (shown, condvar) = &WORKSPACES_SHOWN;
+        *shown.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        condvar.notify_all();
+    }
+}
+
+/// Stops the message loop when Ctrl+C is pressed or the console is closed, so that whimsy
+/// shows hidden windows again before it exits. Nothing can be done if the process is killed
+/// outright.
+#[cfg(windows)]
+fn install_console_handler(message_loop: keybind::MessageLoopHandle) {
+    if CONSOLE_TARGET.set(message_loop).is_err() {
+        return;
+    }
+
+    let installed = unsafe { consoleapi::SetConsoleCtrlHandler(Some(on_console_control), 1) };
+    if installed == 0 {
+        log::error!(
+            "Unable to handle the console being closed; windows on hidden workspaces may stay hidden: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn on_console_control(event: DWORD) -> BOOL {
+    let message_loop = match CONSOLE_TARGET.get() {
+        Some(message_loop) => message_loop,
+        None => return FALSE,
+    };
+
+    if let Err(error) = message_loop.quit() {
+        log::error!("Unable to stop whimsy: {}", error);
+        return FALSE;
+    }
+
+    // For anything but Ctrl+C and Ctrl+Break, Windows ends the process as soon as this
+    // returns, and gives up waiting after five seconds.
+    if event != wincon::CTRL_C_EVENT && event != wincon::CTRL_BREAK_EVENT {
+        let (shown, condvar) = &WORKSPACES_SHOWN;
+        let shown = shown.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = condvar.wait_timeout_while(shown, Duration::from_secs(4), |shown| !*shown);
+    }
+
+    TRUE
+}
+
+/// Prints a response as JSON, exiting with an error code if the action failed.
+#[cfg(windows)]
+fn print_response(response: &ipc::Response) -> Result<()> {
+    println!("{}", serde_json::to_string(response)?);
+
+    if let ipc::Response::Error(_) = response {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Whether an action depends on state that only the running whimsy keeps. Workspaces in
+/// particular would leave windows hidden once a one-shot process exits.
+#[cfg(windows)]
+fn needs_running_instance(action: &config::Action) -> bool {
+    matches!(
+        action,
+        config::Action::Undo
+            | config::Action::Redo
+            | config::Action::Restore
+            | config::Action::SwitchWorkspace(_)
+            | config::Action::MoveToWorkspace(_)
+            | config::Action::EnterMode { .. }
+            | config::Action::ExitMode
+    )
+}
+
+/// Tiles every monitor if the configuration asks for tiling.
+#[cfg(windows)]
+fn tile_if_enabled(executor: &mut action::ActionExecutor<backend::Win32Backend>) {
+    if !executor.tiling().enabled {
+        return;
+    }
+
+    if let Err(error) = executor.tile_all() {
+        log::error!("Unable to tile windows: {}", error);
+    }
+}
+
+/// Watches a configuration file and every file it includes.
+#[cfg(windows)]
+fn watch_config_files(
+    config_files: &[std::path::PathBuf],
+    message_loop: keybind::MessageLoopHandle,
+) -> Vec<watcher::FileWatcher> {
+    config_files
+        .iter()
+        .map(|config_file| {
+            watcher::FileWatcher::spawn(
+                config_file.clone(),
+                watcher::DEFAULT_POLL_INTERVAL,
+                move || {
+                    if let Err(error) = message_loop.notify_config_changed() {
+                        log::error!(
+                            "Unable to notify whimsy of a configuration change: {}",
+                            error
+                        );
+                    }
+                },
+            )
+        })
+        .collect()
+}
+
+/// The hotkeys registered for the keymap, and the chords that could not be registered.
+/// Chords that failed are not tried again until the configuration is reloaded, since
+/// whatever holds them usually still does.
+#[cfg(windows)]
+#[derive(Default)]
+struct Hotkeys {
+    /// The chord of every registered hotkey, by internal ID.
+    chords: HashMap<i32, keybind::Chord>,
+    failed: HashSet<keybind::Chord>,
+}
+
+#[cfg(windows)]
+impl Hotkeys {
+    /// Registers the hotkeys for a configuration from scratch, printing a summary of the
+    /// default bindings that could not be registered, and then brings the hotkeys in line
+    /// with the keymap.
+    fn register(
+        &mut self,
+        kb: &mut keybind::Keybinds,
+        config: &config::Config,
+        keymap: &keymap::Keymap,
+    ) {
+        if let Err(error) = kb.unregister_all() {
+            log::error!("Unable to unregister hotkeys: {}", error);
+        }
+        self.chords.clear();
+        self.failed.clear();
+
+        let chord =
+            |binding: &config::Binding| keybind::Chord::new(binding.key, &binding.modifiers);
+        let registration = kb.register_bindings(&config.bindings);
+        if registration.has_failures() {
+            print!("{}", registration.summary());
+        }
+
+        self.chords.extend(
+            registration
+                .registered()
+                .map(|(id, binding)| (id, chord(binding))),
+        );
+        self.failed
+            .extend(registration.failures().map(|(binding, _)| chord(binding)));
+
+        self.sync(kb, keymap);
+    }
+
+    /// Brings the registered hotkeys in line with the chords the keymap is waiting for,
+    /// leaving hotkeys that are still wanted registered.
+    fn sync(&mut self, kb: &mut keybind::Keybinds, keymap: &keymap::Keymap) {
+        let wanted = keymap.active_chords();
+
+        let unwanted: Vec<i32> = self
+            .chords
+            .iter()
+            .filter(|(_, chord)| !wanted.contains(chord))
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in unwanted {
+            let chord = self.chords.remove(&id).unwrap();
+            if let Err(error) = kb.unregister_keybind(id) {
+                log::error!("Unable to unregister {}: {}", chord, error);
+            }
+        }
+
+        for chord in wanted {
+            if self.failed.contains(&chord)
+                || self.chords.values().any(|registered| *registered == chord)
+            {
+                continue;
+            }
+
+            match kb.register_keybind(chord.key, &chord.modifiers) {
+                Ok(id) => {
+                    self.chords.insert(id, chord);
+                }
+                Err(error) => {
+                    println!(
+                        "Unable to register {}: {}",
+                        chord,
+                        keybind::describe_failure(&error)
+                    );
+                    self.failed.insert(chord);
+                }
+            }
+        }
+    }
+}
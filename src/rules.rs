@@ -0,0 +1,296 @@
+//! Matching of windows against the rules in the configuration. Everything here works on
+//! [`WindowInfo`], so it does not care where the window came from.
+use std::convert::TryFrom;
+
+use crate::backend::WindowInfo;
+use crate::config::{Rule, WindowMatch};
+
+/// A pattern that a window's class, title or executable is matched against. Exact and glob
+/// patterns ignore case, as Windows does for class names and paths; regexes are used as
+/// written.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "PatternSource", into = "PatternSource")]
+pub enum Pattern {
+    Exact(String),
+    /// A glob, where `*` matches any run of characters and `?` matches any one character.
+    Glob(String),
+    Regex(regex::Regex),
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        PatternSource::from(self.clone()) == PatternSource::from(other.clone())
+    }
+}
+
+impl Pattern {
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Exact(expected) => expected.to_lowercase() == text.to_lowercase(),
+            Pattern::Glob(glob) => {
+                let glob: Vec<char> = glob.to_lowercase().chars().collect();
+                let text: Vec<char> = text.to_lowercase().chars().collect();
+                glob_matches(&glob, &text)
+            }
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// How a pattern is written in the configuration file. Regexes are compiled when the
+/// configuration is read, so that a bad regex is reported alongside other configuration
+/// errors.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum PatternSource {
+    Exact(String),
+    Glob(String),
+    Regex(String),
+}
+
+impl TryFrom<PatternSource> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(source: PatternSource) -> Result<Pattern, regex::Error> {
+        Ok(match source {
+            PatternSource::Exact(text) => Pattern::Exact(text),
+            PatternSource::Glob(glob) => Pattern::Glob(glob),
+            PatternSource::Regex(regex) => Pattern::Regex(regex::Regex::new(&regex)?),
+        })
+    }
+}
+
+impl From<Pattern> for PatternSource {
+    fn from(pattern: Pattern) -> PatternSource {
+        match pattern {
+            Pattern::Exact(text) => PatternSource::Exact(text),
+            Pattern::Glob(glob) => PatternSource::Glob(glob),
+            Pattern::Regex(regex) => PatternSource::Regex(regex.as_str().to_owned()),
+        }
+    }
+}
+
+/// Matches a glob against a piece of text. Backtracks to the most recent `*` on a mismatch,
+/// which is enough for globs without character classes.
+fn glob_matches(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last star swallow one more character and try again.
+                Some((star_g, star_t)) => {
+                    g = star_g + 1;
+                    t = star_t + 1;
+                    star = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+impl WindowMatch {
+    /// Whether a window matches every pattern that is set. Executables match on either
+    /// their full path or their file name, so `firefox.exe` matches wherever Firefox is
+    /// installed. A window whose executable is unknown never matches an executable pattern.
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        let field_matches = |pattern: &Option<Pattern>, text: &str| match pattern {
+            Some(pattern) => pattern.matches(text),
+            None => true,
+        };
+
+        let executable = match (&self.executable, &info.executable) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(pattern), Some(path)) => {
+                pattern.matches(&path.to_string_lossy())
+                    || path
+                        .file_name()
+                        .is_some_and(|name| pattern.matches(&name.to_string_lossy()))
+            }
+        };
+
+        field_matches(&self.class, &info.class)
+            && field_matches(&self.title, &info.title)
+            && executable
+    }
+}
+
+/// Returns the first rule that matches a window.
+pub fn find_rule<'a>(rules: &'a [Rule], info: &WindowInfo) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.criteria.matches(info))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::{RuleAction, WindowBehaviour};
+
+    fn glob(glob: &str, text: &str) -> bool {
+        Pattern::Glob(glob.to_owned()).matches(text)
+    }
+
+    fn info(class: &str, title: &str, executable: Option<&str>) -> WindowInfo {
+        WindowInfo {
+            class: class.to_owned(),
+            title: title.to_owned(),
+            executable: executable.map(PathBuf::from),
+            ..WindowInfo::default()
+        }
+    }
+
+    #[test]
+    fn globs_match_literally_without_wildcards() {
+        assert!(glob("notepad", "notepad"));
+        assert!(glob("", ""));
+        assert!(!glob("notepad", "notepad2"));
+        assert!(!glob("notepad2", "notepad"));
+        assert!(!glob("", "notepad"));
+    }
+
+    #[test]
+    fn glob_question_marks_match_one_character() {
+        assert!(glob("note?ad", "notepad"));
+        assert!(glob("???", "abc"));
+        assert!(!glob("???", "ab"));
+        assert!(!glob("???", "abcd"));
+        // Characters, not bytes.
+        assert!(glob("caf?", "café"));
+    }
+
+    #[test]
+    fn glob_stars_match_any_run_of_characters() {
+        assert!(glob("*", ""));
+        assert!(glob("*", "anything"));
+        assert!(glob("* - Mozilla Firefox", "Rust - Mozilla Firefox"));
+        assert!(glob("*.txt - Notepad", "notes.txt - Notepad"));
+        assert!(glob("a**b", "ab"));
+        assert!(!glob("* - Mozilla Firefox", "Mozilla Firefox"));
+        assert!(!glob("*.txt", "notes.txt.bak"));
+    }
+
+    #[test]
+    fn glob_stars_backtrack() {
+        // The first star has to give up characters it took too eagerly.
+        assert!(glob("*ab*ab", "xabyabab"));
+        assert!(glob("*a?c", "abcaxc"));
+        assert!(glob("a*b*c*d", "aXbXbXcXcXd"));
+        assert!(!glob("*ab*ab", "xabyab_"));
+        assert!(!glob("a*b*c*d", "aXbXcX"));
+    }
+
+    #[test]
+    fn exact_and_glob_patterns_ignore_case() {
+        assert!(Pattern::Exact("Notepad".to_owned()).matches("NOTEPAD"));
+        assert!(glob("*FIREFOX", "Mozilla Firefox"));
+        assert!(!Pattern::Regex(regex::Regex::new("^Firefox$").unwrap()).matches("firefox"));
+    }
+
+    #[test]
+    fn regex_patterns_search_the_text() {
+        let pattern = Pattern::Regex(regex::Regex::new(r"\d+ unread").unwrap());
+
+        assert!(pattern.matches("Inbox (12 unread) - Mail"));
+        assert!(!pattern.matches("Inbox - Mail"));
+    }
+
+    #[test]
+    fn patterns_round_trip_through_the_configuration() {
+        let patterns = vec![
+            Pattern::Exact("Notepad".to_owned()),
+            Pattern::Glob("* - Notepad".to_owned()),
+            Pattern::Regex(regex::Regex::new("^(a|b)+$").unwrap()),
+        ];
+
+        let written = serde_yaml::to_string(&patterns).unwrap();
+        let read: Vec<Pattern> = serde_yaml::from_str(&written).unwrap();
+        assert_eq!(read, patterns);
+
+        let read: Pattern = serde_yaml::from_str("glob: '*.txt'").unwrap();
+        assert_eq!(read, Pattern::Glob("*.txt".to_owned()));
+    }
+
+    #[test]
+    fn invalid_regexes_are_rejected_when_read() {
+        assert!(serde_yaml::from_str::<Pattern>("regex: '(unclosed'").is_err());
+    }
+
+    #[test]
+    fn executables_match_by_path_or_file_name() {
+        let firefox = info(
+            "MozillaWindowClass",
+            "Mozilla Firefox",
+            // Forward slashes separate components on every OS.
+            Some("C:/Program Files/Mozilla Firefox/firefox.exe"),
+        );
+        let by_name = WindowMatch {
+            executable: Some(Pattern::Exact("Firefox.exe".to_owned())),
+            ..WindowMatch::default()
+        };
+        let by_path = WindowMatch {
+            executable: Some(Pattern::Glob("C:/Program Files/*".to_owned())),
+            ..WindowMatch::default()
+        };
+        let other = WindowMatch {
+            executable: Some(Pattern::Exact("chrome.exe".to_owned())),
+            ..WindowMatch::default()
+        };
+
+        assert!(by_name.matches(&firefox));
+        assert!(by_path.matches(&firefox));
+        assert!(!other.matches(&firefox));
+
+        // Elevated processes cannot be inspected, so their executable is unknown.
+        assert!(!by_name.matches(&info("MozillaWindowClass", "Mozilla Firefox", None)));
+    }
+
+    #[test]
+    fn every_set_pattern_has_to_match() {
+        let criteria = WindowMatch {
+            class: Some(Pattern::Exact("Notepad".to_owned())),
+            title: Some(Pattern::Glob("*.txt - Notepad".to_owned())),
+            executable: None,
+        };
+
+        assert!(criteria.matches(&info("Notepad", "notes.txt - Notepad", None)));
+        assert!(!criteria.matches(&info("Notepad", "Untitled - Notepad", None)));
+        assert!(!criteria.matches(&info("Edit", "notes.txt - Notepad", None)));
+        assert!(WindowMatch::default().matches(&info("Anything", "", None)));
+    }
+
+    #[test]
+    fn the_first_matching_rule_wins() {
+        let rule = |class: &str, behaviour: WindowBehaviour| Rule {
+            criteria: WindowMatch {
+                class: Some(Pattern::Glob(class.to_owned())),
+                ..WindowMatch::default()
+            },
+            action: RuleAction::Behaviour(behaviour),
+        };
+        let rules = vec![
+            rule("Notepad", WindowBehaviour::Float),
+            rule("*", WindowBehaviour::Ignore),
+        ];
+
+        assert_eq!(
+            find_rule(&rules, &info("Notepad", "", None)),
+            Some(&rules[0])
+        );
+        assert_eq!(find_rule(&rules, &info("Edit", "", None)), Some(&rules[1]));
+        assert_eq!(find_rule(&rules[..1], &info("Edit", "", None)), None);
+    }
+}
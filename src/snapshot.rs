@@ -0,0 +1,304 @@
+//! Saved arrangements of windows that can be restored later, even after the windows have
+//! been closed and reopened. Windows are recognized by what they are rather than by their
+//! handles, which do not survive the window being closed.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::backend::{MonitorInfo, WindowId, WindowInfo};
+use crate::config::{WindowMatch, DEFAULT_CONFIG_PATH};
+use crate::rules::Pattern;
+use crate::window::Rect;
+
+lazy_static::lazy_static! {
+    pub static ref DEFAULT_SNAPSHOT_PATH: PathBuf = DEFAULT_CONFIG_PATH.with_file_name("layouts.yaml");
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("could not access saved layouts: {0}")]
+    IoError(std::io::Error),
+    #[error("could not deserialize saved layouts: {0}")]
+    DeserializeError(serde_yaml::Error),
+    #[error("could not serialize saved layouts: {0}")]
+    SerializeError(serde_yaml::Error),
+}
+
+/// Where a window was when its layout was saved.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SavedWindow {
+    /// The window this entry applies to. Titles are saved exactly, but can be edited into
+    /// globs or regexes by hand.
+    #[serde(rename = "match")]
+    pub criteria: WindowMatch,
+    pub rect: Rect,
+    /// The work area of the monitor the window was on.
+    pub work_area: Rect,
+}
+
+impl SavedWindow {
+    pub fn capture(info: &WindowInfo, rect: Rect, work_area: Rect) -> SavedWindow {
+        let executable = info
+            .executable
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| Pattern::Exact(name.to_string_lossy().into_owned()));
+
+        SavedWindow {
+            criteria: WindowMatch {
+                class: Some(Pattern::Exact(info.class.clone())),
+                title: Some(Pattern::Exact(info.title.clone())),
+                executable,
+            },
+            rect,
+            work_area,
+        }
+    }
+}
+
+/// Every saved layout, by name.
+pub type Snapshots = BTreeMap<String, Vec<SavedWindow>>;
+
+/// Pairs saved windows with open windows. Each open window is used at most once. A window
+/// whose title matches is preferred, but since titles change (to show the open file, for
+/// instance), any window with the right class and executable will do. Returns pairs of an
+/// index into `saved` and the window to move.
+pub fn match_windows(
+    saved: &[SavedWindow],
+    windows: &[(WindowId, WindowInfo)],
+) -> Vec<(usize, WindowId)> {
+    let mut used = vec![false; windows.len()];
+    let mut matches = Vec::new();
+
+    let find = |criteria: &WindowMatch, used: &[bool]| {
+        windows
+            .iter()
+            .zip(used)
+            .position(|((_, info), &used)| !used && criteria.matches(info))
+    };
+
+    // Exact matches are handed out first, so that a loose match for one saved window
+    // cannot take the window that another saved window matches exactly.
+    let mut unmatched = Vec::new();
+    for (saved_index, saved_window) in saved.iter().enumerate() {
+        match find(&saved_window.criteria, &used) {
+            Some(index) => {
+                used[index] = true;
+                matches.push((saved_index, windows[index].0));
+            }
+            None => unmatched.push(saved_index),
+        }
+    }
+
+    for saved_index in unmatched {
+        let loose = WindowMatch {
+            title: None,
+            ..saved[saved_index].criteria.clone()
+        };
+
+        if let Some(index) = find(&loose, &used) {
+            used[index] = true;
+            matches.push((saved_index, windows[index].0));
+        }
+    }
+
+    matches.sort_by_key(|&(saved_index, _)| saved_index);
+    matches
+}
+
+/// Works out where to put a saved window on the current monitors. If the monitor it was
+/// saved on is still there, the window goes back exactly where it was; otherwise it is
+/// mapped onto the monitor whose work area overlaps the saved one the most, or the primary
+/// monitor if none do.
+pub fn restore_rect(saved: &SavedWindow, monitors: &[MonitorInfo]) -> Option<Rect> {
+    if monitors
+        .iter()
+        .any(|monitor| monitor.work_area == saved.work_area)
+    {
+        return Some(saved.rect);
+    }
+
+    let overlapping = monitors
+        .iter()
+        .filter_map(|monitor| {
+            monitor
+                .work_area
+                .intersection(&saved.work_area)
+                .map(|overlap| (monitor, overlap.area()))
+        })
+        .max_by_key(|&(_, area)| area)
+        .map(|(monitor, _)| monitor);
+
+    let target = overlapping
+        .or_else(|| monitors.iter().find(|monitor| monitor.primary))
+        .or_else(|| monitors.first())?;
+
+    Some(saved.rect.map_between(&saved.work_area, &target.work_area))
+}
+
+/// Reads saved layouts. A missing file has no layouts in it.
+pub fn read_snapshots(path: &Path) -> Result<Snapshots, SnapshotError> {
+    if !path.exists() {
+        return Ok(Snapshots::new());
+    }
+
+    let contents = std::fs::read_to_string(path).map_err(SnapshotError::IoError)?;
+    serde_yaml::from_str(&contents).map_err(SnapshotError::DeserializeError)
+}
+
+pub fn write_snapshots(path: &Path, snapshots: &Snapshots) -> Result<(), SnapshotError> {
+    let contents = serde_yaml::to_string(snapshots).map_err(SnapshotError::SerializeError)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(SnapshotError::IoError)?;
+    }
+
+    std::fs::write(path, contents).map_err(SnapshotError::IoError)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::backend::MonitorId;
+
+    const LEFT_WORK_AREA: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+
+    const RIGHT_WORK_AREA: Rect = Rect {
+        left: 1920,
+        top: 0,
+        right: 3840,
+        bottom: 1040,
+    };
+
+    fn info(class: &str, title: &str) -> WindowInfo {
+        WindowInfo {
+            class: class.to_owned(),
+            title: title.to_owned(),
+            executable: Some(PathBuf::from("C:/Windows/notepad.exe")),
+            ..WindowInfo::default()
+        }
+    }
+
+    fn saved(class: &str, title: &str) -> SavedWindow {
+        SavedWindow::capture(
+            &info(class, title),
+            Rect::xyxy(0, 0, 960, 1040),
+            LEFT_WORK_AREA,
+        )
+    }
+
+    fn monitor(id: isize, work_area: Rect, primary: bool) -> MonitorInfo {
+        MonitorInfo {
+            id: MonitorId(id),
+            bounds: work_area,
+            work_area,
+            primary,
+        }
+    }
+
+    #[test]
+    fn capture_matches_by_file_name() {
+        let window = saved("Notepad", "notes.txt - Notepad");
+
+        assert_eq!(
+            window.criteria.executable,
+            Some(Pattern::Exact("notepad.exe".to_owned()))
+        );
+        assert!(window
+            .criteria
+            .matches(&info("Notepad", "notes.txt - Notepad")));
+    }
+
+    #[test]
+    fn exact_titles_are_matched_before_loose_ones() {
+        let saved = vec![
+            saved("Notepad", "todo.txt - Notepad"),
+            saved("Notepad", "notes.txt - Notepad"),
+        ];
+        // The first saved window would loosely match the first open window, but that one
+        // belongs to the second saved window.
+        let windows = vec![
+            (WindowId(1), info("Notepad", "notes.txt - Notepad")),
+            (WindowId(2), info("Notepad", "Untitled - Notepad")),
+        ];
+
+        assert_eq!(
+            match_windows(&saved, &windows),
+            vec![(0, WindowId(2)), (1, WindowId(1))]
+        );
+    }
+
+    #[test]
+    fn each_window_is_used_once() {
+        let saved = vec![
+            saved("Notepad", "notes.txt - Notepad"),
+            saved("Notepad", "notes.txt - Notepad"),
+            saved("Notepad", "notes.txt - Notepad"),
+        ];
+        let windows = vec![
+            (WindowId(1), info("Notepad", "notes.txt - Notepad")),
+            (WindowId(2), info("Notepad", "notes.txt - Notepad")),
+        ];
+
+        assert_eq!(
+            match_windows(&saved, &windows),
+            vec![(0, WindowId(1)), (1, WindowId(2))]
+        );
+    }
+
+    #[test]
+    fn windows_of_another_class_are_not_matched() {
+        let saved = vec![saved("Notepad", "notes.txt - Notepad")];
+        let windows = vec![(WindowId(1), info("Edit", "notes.txt - Notepad"))];
+
+        assert_eq!(match_windows(&saved, &windows), vec![]);
+    }
+
+    #[test]
+    fn windows_go_back_where_they_were_on_the_same_monitor() {
+        let window = saved("Notepad", "notes.txt - Notepad");
+        let monitors = vec![
+            monitor(1, RIGHT_WORK_AREA, true),
+            monitor(2, LEFT_WORK_AREA, false),
+        ];
+
+        assert_eq!(restore_rect(&window, &monitors), Some(window.rect));
+    }
+
+    #[test]
+    fn windows_are_mapped_onto_a_changed_monitor() {
+        let window = saved("Notepad", "notes.txt - Notepad");
+        // The taskbar moved to the left of the same monitor.
+        let monitors = vec![monitor(1, Rect::xyxy(80, 0, 1920, 1080), true)];
+
+        assert_eq!(
+            restore_rect(&window, &monitors),
+            Some(Rect::xyxy(80, 0, 1000, 1080))
+        );
+    }
+
+    #[test]
+    fn windows_on_a_missing_monitor_go_to_the_primary_monitor() {
+        let window = SavedWindow {
+            work_area: RIGHT_WORK_AREA,
+            rect: Rect::xyxy(2880, 0, 3840, 520),
+            ..saved("Notepad", "notes.txt - Notepad")
+        };
+        let monitors = vec![
+            monitor(1, Rect::xyxy(0, -1080, 1920, 0), false),
+            monitor(2, LEFT_WORK_AREA, true),
+        ];
+
+        assert_eq!(
+            restore_rect(&window, &monitors),
+            Some(Rect::xyxy(960, 0, 1920, 520))
+        );
+        assert_eq!(restore_rect(&window, &[]), None);
+    }
+}
@@ -1,16 +1,16 @@
 #![macro_use]
+/// Calls a Win32 function that returns zero on failure. Evaluates to a
+/// `Result<(), Win32Error>`, capturing `GetLastError()` if the call failed.
 macro_rules! evaluate_fallible_winapi {
-    ($e:expr) => {
-        let winapi_success: i32 = $e;
+    ($first:ident $(:: $rest:ident)* ( $($arg:expr),* $(,)? )) => {{
+        let winapi_success: i32 = $first $(:: $rest)* ($($arg),*);
 
         if winapi_success == 0 {
-            log::error!(
-                "Error from winapi in expression {}: {}",
-                stringify!($e),
-                winapi::um::errhandlingapi::GetLastError(),
-            );
-
-            return Err(());
+            Err(crate::error::Win32Error::last_error(concat!(
+                stringify!($first) $(, "::", stringify!($rest))*
+            )))
+        } else {
+            Ok(())
         }
-    };
+    }};
 }
@@ -0,0 +1,887 @@
+//! Checks of configuration files for mistakes that would otherwise only show up as whimsy
+//! misbehaving: problems serde cannot catch, such as bindings that conflict or fractions
+//! that make no sense. Every problem is reported with where it is in the file.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+use yaml_rust::parser::{Event, Parser};
+
+use crate::config::{self, Action, Binding, Config, Fraction, Metric, Mode};
+use crate::keybind::{Chord, Key};
+use crate::keymap::DEFAULT_MODE;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something that is probably a mistake, but that whimsy copes with.
+    Warning,
+    /// Something that stops the configuration from loading, or from working as written.
+    Error,
+}
+
+/// A position in a file. Both lines and columns start at 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    fn from_yaml_error(error: &serde_yaml::Error) -> Diagnostic {
+        let location = error.location().map(|location| Location {
+            line: location.line(),
+            column: location.column(),
+        });
+
+        // serde_yaml puts the location at the end of the message, but it is shown separately.
+        let mut message = error.to_string();
+        if let Some(location) = location {
+            let suffix = format!(" at line {} column {}", location.line, location.column);
+            if message.ends_with(&suffix) {
+                message.truncate(message.len() - suffix.len());
+            }
+        }
+
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            location,
+        }
+    }
+
+    /// Formats the diagnostic for the terminal, quoting the line it refers to.
+    pub fn render(&self, path: &Path, source: &str) -> String {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let mut rendered = format!("{}: {}\n", severity, self.message);
+
+        let location = match self.location {
+            Some(location) => location,
+            None => {
+                writeln!(rendered, "  --> {}", path.display()).unwrap();
+                return rendered;
+            }
+        };
+
+        writeln!(
+            rendered,
+            "  --> {}:{}:{}",
+            path.display(),
+            location.line,
+            location.column
+        )
+        .unwrap();
+
+        if let Some(line) = source.lines().nth(location.line - 1) {
+            let number = location.line.to_string();
+            let gutter = " ".repeat(number.len());
+            writeln!(rendered, "{} |", gutter).unwrap();
+            writeln!(rendered, "{} | {}", number, line).unwrap();
+            writeln!(
+                rendered,
+                "{} | {}^",
+                gutter,
+                " ".repeat(location.column - 1)
+            )
+            .unwrap();
+        }
+
+        rendered
+    }
+}
+
+/// Where each node of a YAML document starts, by path. Paths are written the way serde_yaml
+/// writes them: `bindings[2].action`.
+#[derive(Debug, Default)]
+struct Spans(HashMap<String, Location>);
+
+enum Frame {
+    Mapping {
+        path: String,
+        key: Option<String>,
+        expecting_key: bool,
+    },
+    Sequence {
+        path: String,
+        index: usize,
+    },
+}
+
+impl Spans {
+    /// Records where every node starts. Documents that do not parse are left to serde_yaml to
+    /// report, so this only records what comes before the error.
+    fn collect(source: &str) -> Spans {
+        let mut spans = Spans::default();
+        let mut parser = Parser::new(source.chars());
+        let mut stack: Vec<Frame> = Vec::new();
+
+        while let Ok((event, marker)) = parser.next() {
+            let location = Location {
+                line: marker.line(),
+                column: marker.col() + 1,
+            };
+
+            let path = match stack.last() {
+                None => Some(String::new()),
+                Some(Frame::Mapping {
+                    path,
+                    key: Some(key),
+                    expecting_key: false,
+                }) if path.is_empty() => Some(key.clone()),
+                Some(Frame::Mapping {
+                    path,
+                    key: Some(key),
+                    expecting_key: false,
+                }) => Some(format!("{}.{}", path, key)),
+                Some(Frame::Mapping { .. }) => None,
+                Some(Frame::Sequence { path, index }) => Some(format!("{}[{}]", path, index)),
+            };
+
+            match event {
+                Event::StreamEnd => break,
+                Event::Scalar(text, ..) => {
+                    match stack.last_mut() {
+                        // A value is located at its key, which is on the same line for
+                        // scalars and on the line before for nested mappings and lists.
+                        Some(Frame::Mapping {
+                            path,
+                            key,
+                            expecting_key: true,
+                        }) => {
+                            let key_path = if path.is_empty() {
+                                text.clone()
+                            } else {
+                                format!("{}.{}", path, text)
+                            };
+                            spans.0.entry(key_path).or_insert(location);
+                            *key = Some(text);
+                        }
+                        _ => {
+                            if let Some(path) = path {
+                                spans.0.entry(path).or_insert(location);
+                            }
+                        }
+                    }
+
+                    Self::finish_node(&mut stack);
+                }
+                Event::Alias(_) => Self::finish_node(&mut stack),
+                Event::MappingStart(_) | Event::SequenceStart(_) => {
+                    // Keys that are themselves mappings or sequences get no path; nothing
+                    // in the configuration uses them.
+                    let path = path.unwrap_or_default();
+                    spans.0.entry(path.clone()).or_insert(location);
+
+                    stack.push(match event {
+                        Event::MappingStart(_) => Frame::Mapping {
+                            path,
+                            key: None,
+                            expecting_key: true,
+                        },
+                        _ => Frame::Sequence { path, index: 0 },
+                    });
+                }
+                Event::MappingEnd | Event::SequenceEnd => {
+                    stack.pop();
+                    Self::finish_node(&mut stack);
+                }
+                _ => {}
+            }
+        }
+
+        spans
+    }
+
+    /// Moves the innermost mapping or sequence on past a node that has ended.
+    fn finish_node(stack: &mut [Frame]) {
+        match stack.last_mut() {
+            Some(Frame::Mapping {
+                key, expecting_key, ..
+            }) => {
+                if !*expecting_key {
+                    *key = None;
+                }
+                *expecting_key = !*expecting_key;
+            }
+            Some(Frame::Sequence { index, .. }) => *index += 1,
+            None => {}
+        }
+    }
+
+    /// Finds where a path starts, falling back to the nearest enclosing node that was found.
+    fn locate(&self, mut path: &str) -> Option<Location> {
+        loop {
+            if let Some(&location) = self.0.get(path) {
+                return Some(location);
+            }
+
+            path = &path[..path.rfind(['.', '['])?];
+        }
+    }
+}
+
+struct Checker<'a> {
+    /// Every mode in the configuration, which may come from other files.
+    modes: &'a BTreeMap<String, Mode>,
+    spans: Spans,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, severity: Severity, path: &str, message: String) {
+        let location = self.spans.locate(path);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            location,
+        });
+    }
+
+    /// Describes where a path is, for pointing at a second place in one message.
+    fn describe(&self, path: &str) -> String {
+        match self.spans.locate(path) {
+            Some(location) => format!("line {}", location.line),
+            None => format!("`{}`", path),
+        }
+    }
+
+    /// Reports keys that whimsy does not know about, which serde quietly skips. Only the top
+    /// level and the directives are checked; every other section is a list or a tagged enum.
+    fn check_unknown_keys(&mut self, document: &serde_yaml::Value) {
+        // Serializing the defaults gives every key, without keeping a second list of them.
+        let defaults = serde_yaml::to_value(Config::default()).unwrap();
+        let keys = |value: &serde_yaml::Value| -> Vec<String> {
+            value
+                .as_mapping()
+                .map(|mapping| {
+                    mapping
+                        .iter()
+                        .filter_map(|(key, _)| key.as_str().map(str::to_owned))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let known = keys(&defaults);
+        for key in keys(document) {
+            if !known.contains(&key) {
+                self.report(Severity::Error, &key, format!("unknown setting `{}`", key));
+            }
+        }
+
+        let known = keys(&defaults["directives"]);
+        for key in keys(&document["directives"]) {
+            if !known.contains(&key) {
+                self.report(
+                    Severity::Error,
+                    &format!("directives.{}", key),
+                    format!(
+                        "unknown directive `{}`; the directives are {}",
+                        key,
+                        known.join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_bindings(&mut self, bindings: &[Binding], path: &str, default_mode: bool) {
+        let mut seen: Vec<(Vec<Chord>, String)> = Vec::new();
+
+        for (index, binding) in bindings.iter().enumerate() {
+            let binding_path = format!("{}[{}]", path, index);
+            let sequence = binding.sequence();
+            let written = sequence
+                .iter()
+                .map(Chord::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            // The keymap runs the first binding to complete, so later duplicates and longer
+            // sequences that start with a whole earlier binding are never reached.
+            for (earlier, earlier_path) in &seen {
+                if *earlier == sequence {
+                    self.report(
+                        Severity::Error,
+                        &binding_path,
+                        format!(
+                            "{} is already bound at {}",
+                            written,
+                            self.describe(earlier_path)
+                        ),
+                    );
+                } else if sequence.starts_with(earlier) || earlier.starts_with(&sequence) {
+                    let (shadowed, shadowing) = if sequence.len() > earlier.len() {
+                        (&binding_path, earlier_path)
+                    } else {
+                        (earlier_path, &binding_path)
+                    };
+
+                    self.report(
+                        Severity::Warning,
+                        &binding_path,
+                        format!(
+                            "the sequence at {} can never be finished, because the binding at {} runs as soon as its first chords are pressed",
+                            self.describe(shadowed),
+                            self.describe(shadowing)
+                        ),
+                    );
+                }
+            }
+
+            // Hotkeys take their keys away from every other application. Modes only last
+            // until they are exited, and F13 to F24 are on no keyboard.
+            let typing_key = !(Key::F13 as i32..=Key::F24 as i32).contains(&(binding.key as i32));
+            if default_mode && binding.modifiers.is_empty() && typing_key {
+                self.report(
+                    Severity::Error,
+                    &format!("{}.modifiers", binding_path),
+                    format!(
+                        "{} has no modifiers, so it would stop working in every other application",
+                        sequence[0]
+                    ),
+                );
+            }
+
+            self.check_action(&binding.action, &format!("{}.action", binding_path));
+            seen.push((sequence, binding_path));
+        }
+    }
+
+    fn check_action(&mut self, action: &Action, path: &str) {
+        match action {
+            Action::Push { fraction, .. } => {
+                let fractions = match fraction {
+                    Fraction::Single(fraction) => vec![*fraction],
+                    Fraction::Cycle(fractions) => fractions.clone(),
+                };
+
+                for fraction in fractions {
+                    if fraction <= 1.0 {
+                        self.report(
+                            Severity::Error,
+                            &format!("{}.push.fraction", path),
+                            format!(
+                                "push fraction {} must be greater than 1; 2 is half of the work area, 3 is a third, and so on",
+                                fraction
+                            ),
+                        );
+                    }
+                }
+            }
+            Action::Grid {
+                columns,
+                rows,
+                x,
+                y,
+                ..
+            } => {
+                self.check_grid_axis("columns", "x", *columns, *x, path);
+                self.check_grid_axis("rows", "y", *rows, *y, path);
+            }
+            Action::Nudge { distance, .. } => {
+                self.check_metric(distance, &format!("{}.nudge.distance", path))
+            }
+            Action::Resize { delta, .. } => {
+                self.check_metric(delta, &format!("{}.resize.delta", path))
+            }
+            Action::Center { width, height } => {
+                self.check_metric(width, &format!("{}.center.width", path));
+                self.check_metric(height, &format!("{}.center.height", path));
+            }
+            Action::AlmostMaximize { margin } => {
+                self.check_metric(margin, &format!("{}.almost-maximize.margin", path))
+            }
+            Action::EnterMode { mode }
+                if mode != DEFAULT_MODE && !self.modes.contains_key(mode) =>
+            {
+                self.report(
+                    Severity::Error,
+                    &format!("{}.enter-mode.mode", path),
+                    format!("there is no mode named `{}`", mode),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks one axis of a grid placement. Placing treats an empty grid as one cell and moves
+    /// cells past the grid back onto it, which is never what was meant.
+    fn check_grid_axis(
+        &mut self,
+        cells_field: &str,
+        cell_field: &str,
+        cells: u32,
+        cell: u32,
+        path: &str,
+    ) {
+        if cells == 0 {
+            self.report(
+                Severity::Error,
+                &format!("{}.grid.{}", path, cells_field),
+                format!("a grid needs at least one of its {}", cells_field),
+            );
+        } else if cell >= cells {
+            self.report(
+                Severity::Error,
+                &format!("{}.grid.{}", path, cell_field),
+                format!(
+                    "{} {} is past the end of a grid with {} {}; cells are counted from 0",
+                    cell_field, cell, cells, cells_field
+                ),
+            );
+        }
+    }
+
+    fn check_metric(&mut self, metric: &Metric, path: &str) {
+        if let Metric::Percent(fraction) = *metric {
+            if !(-1.0..=1.0).contains(&fraction) {
+                self.report(
+                    Severity::Error,
+                    &format!("{}.percent", path),
+                    format!(
+                        "percent {} is {}%; percentages are written as fractions, so 50% is 0.5",
+                        fraction,
+                        fraction * 100.0
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Checks the contents of a configuration file. Problems that stop the file from loading are
+/// reported on their own, since nothing else can be checked until they are fixed.
+pub fn validate(source: &str) -> Vec<Diagnostic> {
+    check(source, None)
+}
+
+/// Checks one of the files a configuration was read from. Bindings in the file may enter
+/// modes defined in any of the others.
+pub fn validate_part(source: &str, merged: &Config) -> Vec<Diagnostic> {
+    check(source, Some(merged))
+}
+
+fn check(source: &str, merged: Option<&Config>) -> Vec<Diagnostic> {
+    if config::is_blank(source) {
+        return Vec::new();
+    }
+
+    let document: serde_yaml::Value = match serde_yaml::from_str(source) {
+        Ok(document) => document,
+        Err(error) => return vec![Diagnostic::from_yaml_error(&error)],
+    };
+
+    let config: Config = match serde_yaml::from_str(source) {
+        Ok(config) => config,
+        Err(error) => return vec![Diagnostic::from_yaml_error(&error)],
+    };
+
+    let mut checker = Checker {
+        modes: &merged.unwrap_or(&config).modes,
+        spans: Spans::collect(source),
+        diagnostics: Vec::new(),
+    };
+
+    checker.check_unknown_keys(&document);
+    checker.check_bindings(&config.bindings, "bindings", true);
+    for (name, mode) in &config.modes {
+        if name == DEFAULT_MODE {
+            checker.report(
+                Severity::Error,
+                &format!("modes.{}", name),
+                format!(
+                    "`{}` is the mode of the top-level bindings, so this mode would be ignored; give it another name",
+                    DEFAULT_MODE
+                ),
+            );
+        }
+
+        checker.check_bindings(&mode.bindings, &format!("modes.{}.bindings", name), false);
+    }
+
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.location.is_none(), diagnostic.location));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<(Severity, String, Option<usize>)> {
+        validate(source)
+            .into_iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.severity,
+                    diagnostic.message,
+                    diagnostic.location.map(|location| location.line),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_placements_must_fit_the_grid() {
+        let source = "\
+bindings:
+  - key: a
+    modifiers: [alt]
+    action:
+      grid: {columns: 0, rows: 2, x: 0, y: 2}
+  - key: b
+    modifiers: [alt]
+    action:
+      grid: {columns: 3, rows: 2, x: 3, y: 1}
+";
+
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    Severity::Error,
+                    "a grid needs at least one of its columns".to_owned(),
+                    Some(5)
+                ),
+                (
+                    Severity::Error,
+                    "y 2 is past the end of a grid with 2 rows; cells are counted from 0"
+                        .to_owned(),
+                    Some(5)
+                ),
+                (
+                    Severity::Error,
+                    "x 3 is past the end of a grid with 3 columns; cells are counted from 0"
+                        .to_owned(),
+                    Some(9)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_placements_inside_the_grid_are_fine() {
+        let source = "\
+bindings:
+  - key: a
+    modifiers: [alt]
+    action:
+      grid: {columns: 3, rows: 2, x: 2, y: 1, width: 5}
+";
+
+        assert_eq!(messages(source), vec![]);
+    }
+
+    #[test]
+    fn duplicate_bindings_are_errors() {
+        let source = "\
+bindings:
+  - {key: left, modifiers: [alt, shift], action: maximize}
+  - {key: left, modifiers: [shift, alt], action: restore}
+";
+
+        assert_eq!(
+            messages(source),
+            vec![(
+                Severity::Error,
+                "Alt+Shift+Left is already bound at line 2".to_owned(),
+                Some(3)
+            )]
+        );
+    }
+
+    #[test]
+    fn sequences_shadowed_by_a_shorter_binding_are_warnings() {
+        let source = "\
+bindings:
+  - {key: space, modifiers: [alt], action: maximize}
+  - {key: space, modifiers: [alt], then: [{key: w}], action: restore}
+";
+
+        assert_eq!(
+            messages(source),
+            vec![(
+                Severity::Warning,
+                "the sequence at line 3 can never be finished, because the binding at line 2 runs as soon as its first chords are pressed".to_owned(),
+                Some(3)
+            )]
+        );
+    }
+
+    #[test]
+    fn push_fractions_must_be_greater_than_one() {
+        let source = "\
+bindings:
+  - {key: a, modifiers: [alt], action: {push: {direction: left, fraction: 1.0}}}
+  - {key: b, modifiers: [alt], action: {push: {direction: left, fraction: 0}}}
+  - {key: c, modifiers: [alt], action: {push: {direction: left, fraction: [2, 0.5]}}}
+  - {key: d, modifiers: [alt], action: {push: {direction: left, fraction: [2, 3, 1.5]}}}
+";
+        let explanation =
+            "must be greater than 1; 2 is half of the work area, 3 is a third, and so on";
+
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    Severity::Error,
+                    format!("push fraction 1 {}", explanation),
+                    Some(2)
+                ),
+                (
+                    Severity::Error,
+                    format!("push fraction 0 {}", explanation),
+                    Some(3)
+                ),
+                (
+                    Severity::Error,
+                    format!("push fraction 0.5 {}", explanation),
+                    Some(4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn bindings_without_modifiers_are_errors_in_the_default_mode() {
+        let source = "\
+bindings:
+  - key: left
+    modifiers: []
+    action: maximize
+";
+
+        assert_eq!(
+            messages(source),
+            vec![(
+                Severity::Error,
+                "Left has no modifiers, so it would stop working in every other application"
+                    .to_owned(),
+                Some(3)
+            )]
+        );
+    }
+
+    #[test]
+    fn bindings_without_modifiers_are_allowed_in_modes_and_on_spare_keys() {
+        let source = "\
+bindings:
+  - {key: f13, modifiers: [], action: maximize}
+  - {key: f24, modifiers: [], action: {enter-mode: {mode: resize}}}
+modes:
+  resize:
+    bindings:
+      - {key: left, modifiers: [], action: restore}
+      - {key: escape, modifiers: [], action: exit-mode}
+";
+
+        assert_eq!(messages(source), vec![]);
+    }
+
+    #[test]
+    fn unknown_settings_and_directives_are_errors() {
+        let source = "\
+binding: []
+directives:
+  live-reload: true
+  sequence-timeut: 500
+";
+
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    Severity::Error,
+                    "unknown setting `binding`".to_owned(),
+                    Some(1)
+                ),
+                (
+                    Severity::Error,
+                    "unknown directive `sequence-timeut`; the directives are live-reload, sequence-timeout"
+                        .to_owned(),
+                    Some(4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn percentages_must_be_fractions() {
+        let source = "\
+bindings:
+  - {key: a, modifiers: [alt], action: {nudge: {direction: up, distance: {percent: 10}}}}
+  - {key: b, modifiers: [alt], action: {resize: {edge: up, delta: {percent: -1.5}}}}
+  - {key: c, modifiers: [alt], action: {almost-maximize: {margin: {percent: -1}}}}
+  - {key: d, modifiers: [alt], action: {center: {width: {percent: 1}, height: {absolute: 800}}}}
+";
+
+        assert_eq!(
+            messages(source),
+            vec![
+                (
+                    Severity::Error,
+                    "percent 10 is 1000%; percentages are written as fractions, so 50% is 0.5"
+                        .to_owned(),
+                    Some(2)
+                ),
+                (
+                    Severity::Error,
+                    "percent -1.5 is -150%; percentages are written as fractions, so 50% is 0.5"
+                        .to_owned(),
+                    Some(3)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn entering_a_missing_mode_is_an_error() {
+        let source = "\
+bindings:
+  - {key: r, modifiers: [alt], action: {enter-mode: {mode: resize}}}
+  - {key: d, modifiers: [alt], action: {enter-mode: {mode: default}}}
+  - {key: m, modifiers: [alt], action: {enter-mode: {mode: move}}}
+modes:
+  move:
+    bindings: []
+";
+
+        assert_eq!(
+            messages(source),
+            vec![(
+                Severity::Error,
+                "there is no mode named `resize`".to_owned(),
+                Some(2)
+            )]
+        );
+    }
+
+    #[test]
+    fn modes_from_other_files_can_be_entered() {
+        let source = "\
+bindings:
+  - {key: r, modifiers: [alt], action: {enter-mode: {mode: resize}}}
+";
+        let merged: Config = serde_yaml::from_str("modes: {resize: {bindings: []}}").unwrap();
+
+        assert_eq!(validate_part(source, &merged), vec![]);
+    }
+
+    #[test]
+    fn serde_errors_keep_their_location() {
+        let source = "\
+bindings:
+  - key: left
+    modifiers: [alt]
+    action: shove
+";
+
+        let diagnostics = validate(source);
+        assert_eq!(diagnostics.len(), 1);
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(
+            diagnostic.location,
+            Some(Location {
+                line: 4,
+                column: 13
+            })
+        );
+        assert!(diagnostic.message.contains("unknown variant `shove`"));
+        assert!(!diagnostic.message.contains(" at line "));
+    }
+
+    #[test]
+    fn render_quotes_the_line_and_points_at_the_column() {
+        let source = "\
+bindings:
+  - key: left
+    modifiers: [alt]
+    action: shove
+";
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            message: "something is off".to_owned(),
+            location: Some(Location {
+                line: 4,
+                column: 13,
+            }),
+        };
+
+        assert_eq!(
+            diagnostic.render(Path::new("whimsy.yaml"), source),
+            "\
+warning: something is off
+  --> whimsy.yaml:4:13
+  |
+4 |     action: shove
+  |             ^
+"
+        );
+
+        let diagnostic = Diagnostic {
+            location: None,
+            ..diagnostic
+        };
+        assert_eq!(
+            diagnostic.render(Path::new("whimsy.yaml"), source),
+            "warning: something is off\n  --> whimsy.yaml\n"
+        );
+    }
+
+    #[test]
+    fn render_widens_the_gutter_for_long_line_numbers() {
+        let source = format!("{}bindings: 3\n", "\n".repeat(11));
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            message: "wrong".to_owned(),
+            location: Some(Location {
+                line: 12,
+                column: 1,
+            }),
+        };
+
+        assert_eq!(
+            diagnostic.render(Path::new("whimsy.yaml"), &source),
+            "\
+error: wrong
+  --> whimsy.yaml:12:1
+   |
+12 | bindings: 3
+   | ^
+"
+        );
+    }
+
+    #[test]
+    fn modes_cannot_be_named_default() {
+        let source = "\
+bindings:
+  - {key: r, modifiers: [alt], action: maximize}
+modes:
+  default:
+    bindings:
+      - {key: left, modifiers: [], action: undo}
+";
+
+        assert_eq!(
+            messages(source),
+            vec![(
+                Severity::Error,
+                "`default` is the mode of the top-level bindings, so this mode would be ignored; give it another name".to_owned(),
+                Some(4)
+            )]
+        );
+    }
+}
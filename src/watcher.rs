@@ -0,0 +1,191 @@
+//! Watches a file for modifications by polling its metadata on a background thread.
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched file's metadata is checked.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Identifies a version of the file's contents without reading them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+fn stamp(path: &PathBuf) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+    })
+}
+
+/// Decides when a file has changed from stamps taken at regular intervals.
+#[derive(Debug)]
+struct Debounce {
+    /// The stamp of the version that was last reported, or that was there to begin with.
+    reported: Option<FileStamp>,
+    /// The stamp taken at the previous interval.
+    previous: Option<FileStamp>,
+}
+
+impl Debounce {
+    fn new(initial: Option<FileStamp>) -> Debounce {
+        Debounce {
+            reported: initial,
+            previous: initial,
+        }
+    }
+
+    /// Takes the stamp of the file at the next interval, and returns whether a change
+    /// should be reported.
+    ///
+    /// Editors often write files in several steps, so a change is only reported once the
+    /// file has stopped changing for a full interval. A missing file is most likely
+    /// mid-save, and is not reported at all.
+    fn observe(&mut self, current: Option<FileStamp>) -> bool {
+        let changed = current.is_some() && current == self.previous && current != self.reported;
+        if changed {
+            self.reported = current;
+        }
+
+        self.previous = current;
+        changed
+    }
+}
+
+/// Calls a function whenever the watched file changes. The watcher thread stops when this
+/// is dropped.
+pub struct FileWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileWatcher {
+    pub fn spawn<F>(path: PathBuf, interval: Duration, on_change: F) -> FileWatcher
+    where
+        F: Fn() + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = std::thread::spawn(move || {
+            let mut debounce = Debounce::new(stamp(&path));
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+
+                if debounce.observe(stamp(&path)) {
+                    log::debug!("Watched file {} changed", path.display());
+                    on_change();
+                }
+            }
+        });
+
+        FileWatcher {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A distinct version of the file.
+    fn version(seconds: u64, len: u64) -> Option<FileStamp> {
+        Some(FileStamp {
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+            len,
+        })
+    }
+
+    /// Feeds stamps to a debounce, returning the intervals at which changes were reported.
+    fn reports(initial: Option<FileStamp>, stamps: &[Option<FileStamp>]) -> Vec<usize> {
+        let mut debounce = Debounce::new(initial);
+        stamps
+            .iter()
+            .enumerate()
+            .filter(|&(_, &stamp)| debounce.observe(stamp))
+            .map(|(interval, _)| interval)
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_files_are_not_reported() {
+        assert!(reports(version(1, 10), &[version(1, 10); 5]).is_empty());
+        assert!(reports(None, &[None; 5]).is_empty());
+    }
+
+    #[test]
+    fn a_save_is_reported_once_the_file_settles() {
+        let stamps = [
+            version(1, 10),
+            version(2, 12),
+            version(2, 12),
+            version(2, 12),
+            version(2, 12),
+        ];
+
+        assert_eq!(reports(version(1, 10), &stamps), vec![2]);
+    }
+
+    #[test]
+    fn a_save_in_several_steps_is_reported_once() {
+        // Truncated, then written, then touched again, within consecutive intervals.
+        let stamps = [
+            version(2, 0),
+            version(2, 8),
+            version(3, 12),
+            version(3, 12),
+            version(3, 12),
+        ];
+
+        assert_eq!(reports(version(1, 10), &stamps), vec![3]);
+    }
+
+    #[test]
+    fn a_file_that_is_replaced_is_reported_once() {
+        // Saved by writing a new file and renaming it over the old one.
+        let stamps = [None, version(2, 12), version(2, 12), version(2, 12)];
+        assert_eq!(reports(version(1, 10), &stamps), vec![2]);
+
+        // Put back as it was.
+        let stamps = [None, version(1, 10), version(1, 10)];
+        assert!(reports(version(1, 10), &stamps).is_empty());
+    }
+
+    #[test]
+    fn a_file_that_appears_is_reported() {
+        let stamps = [None, version(1, 10), version(1, 10), version(1, 10)];
+
+        assert_eq!(reports(None, &stamps), vec![2]);
+    }
+
+    #[test]
+    fn every_settled_save_is_reported() {
+        let stamps = [
+            version(2, 12),
+            version(2, 12),
+            version(3, 14),
+            version(3, 14),
+            version(2, 12),
+            version(2, 12),
+        ];
+
+        assert_eq!(reports(version(1, 10), &stamps), vec![1, 3, 5]);
+    }
+}
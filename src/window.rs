@@ -1,18 +1,78 @@
 /// Rustified abstraction layer over winapi for interacting with (top-level) windows.
+#[cfg(windows)]
 use winapi::um::winuser;
 
+#[cfg(windows)]
+use crate::backend::{MonitorId, MonitorInfo, WindowId, WindowInfo};
 use crate::config::Direction;
+#[cfg(windows)]
+use crate::error::{Error, Result, Win32Error};
 
+#[cfg(windows)]
 type WindowHandle = winapi::shared::windef::HWND;
+#[cfg(windows)]
 type MonitorHandle = winapi::shared::windef::HMONITOR;
+#[cfg(windows)]
 type Win32Rect = winapi::shared::windef::RECT;
 
-/// This type will be returned from methods that invoke fallible Win32 errors.
-/// The error, if any, is logged to the output if logging is enabled, but it is
-/// not exposed because it is not particularly useful to callers.
-pub type Win32Result<T> = Result<T, ()>;
+/// Space left around placed windows, in pixels.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Gaps {
+    /// Space between windows and the left and right edges of the work area.
+    pub outer_horizontal: i32,
+    /// Space between windows and the top and bottom edges of the work area.
+    pub outer_vertical: i32,
+    /// Space between horizontally adjacent windows.
+    pub inner_horizontal: i32,
+    /// Space between vertically adjacent windows.
+    pub inner_vertical: i32,
+}
+
+impl Gaps {
+    /// Returns the part of a work area that windows are placed in, inside the outer gaps.
+    pub fn placement_area(&self, work_area: &Rect) -> Rect {
+        work_area.inset(self.outer_horizontal, self.outer_vertical)
+    }
+}
+
+/// The widest invisible border that is believed. Resize borders are 7 pixels wide at 100%
+/// scaling, and grow with it.
+pub const MAXIMUM_BORDER: i32 = 64;
+
+/// The invisible borders around a window's visible frame. Since Windows 10, most windows
+/// have invisible resize borders that are included in the rects Win32 reports and accepts,
+/// but that users cannot see.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Borders {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Borders {
+    /// Computes the borders between a window's outer rect, as reported by `GetWindowRect`,
+    /// and its visible frame, as reported by DWM.
+    pub fn between(outer_rect: &Rect, frame_rect: &Rect) -> Borders {
+        Borders {
+            left: frame_rect.left - outer_rect.left,
+            top: frame_rect.top - outer_rect.top,
+            right: outer_rect.right - frame_rect.right,
+            bottom: outer_rect.bottom - frame_rect.bottom,
+        }
+    }
 
-#[derive(Debug, Copy, Clone)]
+    /// Whether these borders could really be invisible borders. Borders that are negative or
+    /// wider than [`MAXIMUM_BORDER`] mean that the two rects were not measured in the same
+    /// coordinates, e.g. because one of them was scaled for DPI.
+    pub fn is_plausible(&self) -> bool {
+        [self.left, self.top, self.right, self.bottom]
+            .iter()
+            .all(|border| (0..=MAXIMUM_BORDER).contains(border))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Rect {
     pub left: i32,
     pub top: i32,
@@ -76,38 +136,437 @@ impl Rect {
                 width,
                 height_slice,
             ),
+            // Corners take the width of the horizontal slice and the height of the vertical one.
+            Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight => {
+                let (vertical, horizontal) = direction.split();
+                let vertical_slice = self.slice_rect(vertical.unwrap(), slice_factor);
+                let horizontal_slice = self.slice_rect(horizontal.unwrap(), slice_factor);
+
+                Rect::xyxy(
+                    horizontal_slice.left,
+                    vertical_slice.top,
+                    horizontal_slice.right,
+                    vertical_slice.bottom,
+                )
+            }
+        }
+    }
+
+    /// Returns a rect of the given size, centered in this rect. Sizes larger than this rect
+    /// are clamped to it.
+    pub fn centered(&self, width: i32, height: i32) -> Rect {
+        let (outer_width, outer_height) = self.wh();
+        let width = width.clamp(0, outer_width);
+        let height = height.clamp(0, outer_height);
+
+        Rect::xywh(
+            self.left + (outer_width - width) / 2,
+            self.top + (outer_height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// Shrinks this rect by `horizontal` pixels on the left and right, and `vertical` pixels
+    /// on the top and bottom. Negative amounts grow it.
+    pub fn inset(&self, horizontal: i32, vertical: i32) -> Rect {
+        let (width, height) = self.wh();
+        let horizontal = horizontal.min(width / 2);
+        let vertical = vertical.min(height / 2);
+
+        Rect::xyxy(
+            self.left + horizontal,
+            self.top + vertical,
+            self.right - horizontal,
+            self.bottom - vertical,
+        )
+    }
+
+    /// Leaves room for the inner gaps around a rect placed inside `area`. Edges that lie on
+    /// the boundary of `area` stay where they are; edges inside it move inwards by half an
+    /// inner gap, so that two rects sharing an edge end up a full gap apart.
+    pub fn apply_inner_gaps(&self, area: &Rect, gaps: &Gaps) -> Rect {
+        // Left and top edges take the larger half of an odd gap, so the halves always add up
+        // to the full gap.
+        let leading = |gap: i32| gap - gap / 2;
+        let trailing = |gap: i32| gap / 2;
+        let mut rect = *self;
+
+        if self.left > area.left {
+            rect.left += leading(gaps.inner_horizontal);
+        }
+        if self.right < area.right {
+            rect.right -= trailing(gaps.inner_horizontal);
+        }
+        if self.top > area.top {
+            rect.top += leading(gaps.inner_vertical);
+        }
+        if self.bottom < area.bottom {
+            rect.bottom -= trailing(gaps.inner_vertical);
+        }
+
+        // Gaps larger than the rect itself collapse it rather than turning it inside out.
+        rect.right = rect.right.max(rect.left);
+        rect.bottom = rect.bottom.max(rect.top);
+        rect
+    }
+
+    /// Grows this rect by a set of borders. This turns a visible frame into the outer rect
+    /// that Win32 expects.
+    pub fn expand_by(&self, borders: &Borders) -> Rect {
+        Rect::xyxy(
+            self.left - borders.left,
+            self.top - borders.top,
+            self.right + borders.right,
+            self.bottom + borders.bottom,
+        )
+    }
+
+    /// Shrinks this rect by a set of borders. This turns an outer rect from Win32 into the
+    /// visible frame.
+    pub fn shrink_by(&self, borders: &Borders) -> Rect {
+        Rect::xyxy(
+            self.left + borders.left,
+            self.top + borders.top,
+            self.right - borders.right,
+            self.bottom - borders.bottom,
+        )
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32) -> Rect {
+        Rect {
+            left: self.left + dx,
+            top: self.top + dy,
+            right: self.right + dx,
+            bottom: self.bottom + dy,
         }
     }
 
+    /// Maps this rect from one containing rect to another, preserving its position and size
+    /// relative to the container.
+    pub fn map_between(&self, from: &Rect, to: &Rect) -> Rect {
+        let (from_width, from_height) = from.wh();
+        let (to_width, to_height) = to.wh();
+
+        let scale =
+            |value: i32, from_origin: i32, from_length: i32, to_origin: i32, to_length: i32| {
+                if from_length == 0 {
+                    return to_origin;
+                }
+
+                let offset = (value - from_origin) as f64 * to_length as f64 / from_length as f64;
+                to_origin + offset.round() as i32
+            };
+
+        Rect::xyxy(
+            scale(self.left, from.left, from_width, to.left, to_width),
+            scale(self.top, from.top, from_height, to.top, to_height),
+            scale(self.right, from.left, from_width, to.left, to_width),
+            scale(self.bottom, from.top, from_height, to.top, to_height),
+        )
+    }
+
+    /// Divides this rect into a `columns` by `rows` grid and returns the rect covering the
+    /// span of `width` by `height` cells whose top-left cell is at (`x`, `y`). Spans that
+    /// extend past the grid are clamped to it. Cell edges are rounded so that adjacent
+    /// spans share an edge exactly.
+    pub fn grid_span(
+        &self,
+        columns: u32,
+        rows: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Rect {
+        let (total_width, total_height) = self.wh();
+        let (left, right) = grid_edges(self.left, total_width, columns, x, width);
+        let (top, bottom) = grid_edges(self.top, total_height, rows, y, height);
+
+        Rect::xyxy(left, top, right, bottom)
+    }
+
+    /// Moves one edge of this rect outward by `amount` pixels, or inward if `amount` is
+    /// negative. The edge will not be moved past `limit` (though an edge that is already
+    /// past it is not pulled back), and the rect will not shrink to less than `min_size`
+    /// along the edge's axis.
+    pub fn resize_edge(
+        &self,
+        edge: Direction,
+        amount: i32,
+        limit: Option<&Rect>,
+        min_size: i32,
+    ) -> Rect {
+        let (width, height) = self.wh();
+        let mut rect = *self;
+
+        match edge {
+            Direction::Up => {
+                let mut top = self.top - amount;
+                if let Some(limit) = limit {
+                    top = top.max(limit.top.min(self.top));
+                }
+                rect.top = top.min(self.bottom - min_size.min(height));
+            }
+            Direction::Down => {
+                let mut bottom = self.bottom + amount;
+                if let Some(limit) = limit {
+                    bottom = bottom.min(limit.bottom.max(self.bottom));
+                }
+                rect.bottom = bottom.max(self.top + min_size.min(height));
+            }
+            Direction::Left => {
+                let mut left = self.left - amount;
+                if let Some(limit) = limit {
+                    left = left.max(limit.left.min(self.left));
+                }
+                rect.left = left.min(self.right - min_size.min(width));
+            }
+            Direction::Right => {
+                let mut right = self.right + amount;
+                if let Some(limit) = limit {
+                    right = right.min(limit.right.max(self.right));
+                }
+                rect.right = right.max(self.left + min_size.min(width));
+            }
+            // Corners move both of their edges.
+            Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight => {
+                let (vertical, horizontal) = edge.split();
+                rect = self
+                    .resize_edge(vertical.unwrap(), amount, limit, min_size)
+                    .resize_edge(horizontal.unwrap(), amount, limit, min_size);
+            }
+        }
+
+        rect
+    }
+
     pub fn nudge(&self, direction: Direction, amount: i32) -> Rect {
+        let (x, y) = direction.components();
+        self.offset(x * amount, y * amount)
+    }
+
+    pub fn area(&self) -> i64 {
         let (width, height) = self.wh();
-        match direction {
-            Direction::Up => Rect::xywh(self.left, self.top - amount, width, height),
-            Direction::Down => Rect::xywh(self.left, self.top + amount, width, height),
-            Direction::Left => Rect::xywh(self.left - amount, self.top, width, height),
-            Direction::Right => Rect::xywh(self.left + amount, self.top, width, height),
+        width as i64 * height as i64
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        (
+            self.left + (self.right - self.left) / 2,
+            self.top + (self.bottom - self.top) / 2,
+        )
+    }
+
+    /// Returns the overlapping region of two rects, or `None` if they do not overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right.min(other.right);
+        let bottom = self.bottom.min(other.bottom);
+
+        if left < right && top < bottom {
+            Some(Rect {
+                left,
+                top,
+                right,
+                bottom,
+            })
+        } else {
+            None
         }
     }
 
+    #[cfg(windows)]
     fn from_win32_rect(rect: Win32Rect) -> Rect {
         Rect::xyxy(rect.left, rect.top, rect.right, rect.bottom)
     }
 }
 
+/// Computes the start and end of a span of grid cells along one axis.
+fn grid_edges(origin: i32, length: i32, cells: u32, start: u32, span: u32) -> (i32, i32) {
+    let cells = cells.max(1);
+    let start = start.min(cells - 1);
+    let end = start.saturating_add(span.max(1)).min(cells);
+
+    let edge = |cell: u32| origin + (length as i64 * cell as i64 / cells as i64) as i32;
+    (edge(start), edge(end))
+}
+
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct Window {
     handle: WindowHandle,
 }
 
+#[cfg(windows)]
 impl Window {
     fn from_window_handle(handle: WindowHandle) -> Window {
         Window { handle }
     }
 
-    pub fn get_rect(&self) -> Win32Result<Rect> {
+    pub fn from_id(id: WindowId) -> Window {
+        Window::from_window_handle(id.0 as WindowHandle)
+    }
+
+    pub fn id(&self) -> WindowId {
+        WindowId(self.handle as isize)
+    }
+
+    fn error(&self, error: Win32Error) -> Error {
+        Error::Window {
+            window: self.id(),
+            error,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        unsafe { winuser::IsWindow(self.handle) != 0 }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        unsafe { winuser::IsWindowVisible(self.handle) != 0 }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        unsafe { winuser::IsIconic(self.handle) != 0 }
+    }
+
+    /// Whether DWM is hiding the window even though it is visible as far as Win32 is
+    /// concerned. This is the case for suspended UWP apps and windows on other virtual
+    /// desktops.
+    pub fn is_cloaked(&self) -> bool {
+        let mut cloaked: u32 = 0;
+        let result = unsafe {
+            winapi::um::dwmapi::DwmGetWindowAttribute(
+                self.handle,
+                winapi::um::dwmapi::DWMWA_CLOAKED,
+                &mut cloaked as *mut u32 as *mut winapi::ctypes::c_void,
+                std::mem::size_of::<u32>() as u32,
+            )
+        };
+
+        result >= 0 && cloaked != 0
+    }
+
+    /// Whether the window belongs to another window, as dialogs and popups do.
+    pub fn is_owned(&self) -> bool {
+        unsafe { !winuser::GetWindow(self.handle, winuser::GW_OWNER).is_null() }
+    }
+
+    pub fn get_class_name(&self) -> Result<String> {
+        // Class names are limited to 256 characters.
+        let mut buffer = [0u16; 257];
+        let length = unsafe {
+            winuser::GetClassNameW(self.handle, buffer.as_mut_ptr(), buffer.len() as i32)
+        };
+
+        if length == 0 {
+            return Err(self.error(Win32Error::last_error("winuser::GetClassNameW")));
+        }
+
+        Ok(String::from_utf16_lossy(&buffer[..length as usize]))
+    }
+
+    /// Returns the window's title. Windows without a title have an empty one.
+    pub fn get_title(&self) -> String {
+        unsafe {
+            let length = winuser::GetWindowTextLengthW(self.handle);
+            let mut buffer = vec![0u16; length as usize + 1];
+            let copied =
+                winuser::GetWindowTextW(self.handle, buffer.as_mut_ptr(), buffer.len() as i32);
+
+            String::from_utf16_lossy(&buffer[..copied.max(0) as usize])
+        }
+    }
+
+    pub fn get_process_id(&self) -> u32 {
+        let mut process_id = 0;
+        unsafe {
+            winuser::GetWindowThreadProcessId(self.handle, &mut process_id);
+        }
+        process_id
+    }
+
+    /// Returns the path of the executable for the window's process, if whimsy is allowed to
+    /// inspect the process.
+    pub fn get_executable_path(&self) -> Option<std::path::PathBuf> {
+        use std::os::windows::ffi::OsStringExt;
+
+        unsafe {
+            let process = winapi::um::processthreadsapi::OpenProcess(
+                winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION,
+                0,
+                self.get_process_id(),
+            );
+
+            if process.is_null() {
+                log::debug!(
+                    "Unable to open the process of window {:?}: {}",
+                    self.id(),
+                    Win32Error::last_error("processthreadsapi::OpenProcess")
+                );
+                return None;
+            }
+
+            let mut buffer = [0u16; winapi::shared::minwindef::MAX_PATH * 4];
+            let mut length = buffer.len() as u32;
+            let result =
+                evaluate_fallible_winapi!(winapi::um::winbase::QueryFullProcessImageNameW(
+                    process,
+                    0,
+                    buffer.as_mut_ptr(),
+                    &mut length
+                ));
+            winapi::um::handleapi::CloseHandle(process);
+
+            match result {
+                Ok(()) => Some(std::ffi::OsString::from_wide(&buffer[..length as usize]).into()),
+                Err(error) => {
+                    log::debug!(
+                        "Unable to find the executable of window {:?}: {}",
+                        self.id(),
+                        error
+                    );
+                    None
+                }
+            }
+        }
+    }
+
+    fn get_style(&self) -> u32 {
+        unsafe { winuser::GetWindowLongPtrW(self.handle, winuser::GWL_STYLE) as u32 }
+    }
+
+    fn get_extended_style(&self) -> u32 {
+        unsafe { winuser::GetWindowLongPtrW(self.handle, winuser::GWL_EXSTYLE) as u32 }
+    }
+
+    pub fn get_info(&self) -> Result<WindowInfo> {
+        Ok(WindowInfo {
+            class: self.get_class_name()?,
+            title: self.get_title(),
+            process_id: self.get_process_id(),
+            executable: self.get_executable_path(),
+            tool_window: self.get_extended_style() & winuser::WS_EX_TOOLWINDOW != 0,
+            fixed_size: self.get_style() & winuser::WS_THICKFRAME == 0,
+            borderless: self.get_style() & winuser::WS_CAPTION != winuser::WS_CAPTION,
+        })
+    }
+
+    /// Whether the window is one that a user would think of as a window on the desktop,
+    /// and so should be included when arranging windows.
+    pub fn is_arrangeable(&self) -> bool {
+        self.is_visible() && !self.is_minimized() && !self.is_cloaked() && !self.is_owned()
+    }
+
+    /// Returns the rect of the window as Win32 sees it, including the invisible resize
+    /// borders.
+    fn get_outer_rect(&self) -> Result<Rect> {
         unsafe {
             let mut winapi_rect: Win32Rect = std::mem::zeroed();
-            evaluate_fallible_winapi!(winuser::GetWindowRect(self.handle, &mut winapi_rect));
+            evaluate_fallible_winapi!(winuser::GetWindowRect(self.handle, &mut winapi_rect))
+                .map_err(|error| self.error(error))?;
 
             Ok(Rect {
                 left: winapi_rect.left,
@@ -118,10 +577,67 @@ impl Window {
         }
     }
 
-    pub fn set_rect(&mut self, rect: Rect) -> Win32Result<()> {
+    /// Returns the visible frame of the window, as drawn by DWM.
+    fn get_frame_rect(&self) -> Result<Rect> {
+        unsafe {
+            let mut winapi_rect: Win32Rect = std::mem::zeroed();
+            let result = winapi::um::dwmapi::DwmGetWindowAttribute(
+                self.handle,
+                winapi::um::dwmapi::DWMWA_EXTENDED_FRAME_BOUNDS,
+                &mut winapi_rect as *mut Win32Rect as *mut winapi::ctypes::c_void,
+                // cast is safe, RECT is 16 bytes
+                std::mem::size_of::<Win32Rect>() as u32,
+            );
+
+            if result < 0 {
+                return Err(self.error(Win32Error::from_hresult(
+                    "dwmapi::DwmGetWindowAttribute",
+                    result,
+                )));
+            }
+
+            Ok(Rect::from_win32_rect(winapi_rect))
+        }
+    }
+
+    /// Measures the invisible borders of the window, given its outer rect. If DWM cannot
+    /// tell us where the visible frame is, or the borders make no sense, the window is
+    /// assumed to have no invisible borders.
+    fn get_borders(&self, outer_rect: &Rect) -> Borders {
+        match self.get_frame_rect() {
+            Ok(frame_rect) => {
+                let borders = Borders::between(outer_rect, &frame_rect);
+                if borders.is_plausible() {
+                    borders
+                } else {
+                    log::debug!(
+                        "Ignoring implausible borders {:?} of window {:?}",
+                        borders,
+                        self.id()
+                    );
+                    Borders::default()
+                }
+            }
+            Err(error) => {
+                log::debug!("Unable to measure window borders: {}", error);
+                Borders::default()
+            }
+        }
+    }
+
+    /// Returns the rect of the window's visible frame.
+    pub fn get_rect(&self) -> Result<Rect> {
+        let outer_rect = self.get_outer_rect()?;
+        Ok(outer_rect.shrink_by(&self.get_borders(&outer_rect)))
+    }
+
+    /// Moves the window so that its visible frame covers `rect`.
+    pub fn set_rect(&mut self, rect: Rect) -> Result<()> {
         // Size and position will change, Z order will not. We don't want to activate the window,
         // and this call should be non-blocking.
         let flags = winuser::SWP_NOZORDER | winuser::SWP_NOACTIVATE | winuser::SWP_ASYNCWINDOWPOS;
+        let outer_rect = self.get_outer_rect()?;
+        let rect = rect.expand_by(&self.get_borders(&outer_rect));
         let (w, h) = rect.wh();
 
         unsafe {
@@ -133,12 +649,37 @@ impl Window {
                 w,
                 h,
                 flags
-            ));
+            ))
+            .map_err(|error| self.error(error))?;
         }
 
         Ok(())
     }
 
+    /// Brings the window to the front and gives it keyboard focus. Windows only lets a
+    /// process do this in response to user input, such as a hotkey.
+    pub fn activate(&mut self) -> Result<()> {
+        unsafe {
+            evaluate_fallible_winapi!(winuser::SetForegroundWindow(self.handle))
+                .map_err(|error| self.error(error))
+        }
+    }
+
+    /// Hides the window, taking it off the taskbar until it is shown again.
+    pub fn hide(&mut self) {
+        // ShowWindow returns whether the window was visible before, not whether it failed.
+        unsafe {
+            winuser::ShowWindow(self.handle, winuser::SW_HIDE);
+        }
+    }
+
+    /// Shows a hidden window without activating it.
+    pub fn show(&mut self) {
+        unsafe {
+            winuser::ShowWindow(self.handle, winuser::SW_SHOWNA);
+        }
+    }
+
     pub fn get_monitor(&self) -> Monitor {
         unsafe {
             Monitor {
@@ -148,6 +689,30 @@ impl Window {
     }
 }
 
+/// Makes whimsy see every monitor in physical pixels. DWM always reports visible frames in
+/// physical pixels, but processes that do not declare DPI awareness get every other rect
+/// scaled on scaled monitors, and the two would not line up.
+#[cfg(windows)]
+pub fn enable_dpi_awareness() {
+    unsafe {
+        if winuser::SetProcessDpiAwarenessContext(
+            winapi::shared::windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+        ) != 0
+        {
+            return;
+        }
+
+        // Versions of Windows 10 before the Creators Update only have system awareness,
+        // which is still right on the primary monitor.
+        log::debug!(
+            "Unable to become per-monitor DPI aware: {}",
+            Win32Error::last_error("winuser::SetProcessDpiAwarenessContext")
+        );
+        winuser::SetProcessDPIAware();
+    }
+}
+
+#[cfg(windows)]
 pub fn get_focused_window() -> Option<Window> {
     unsafe {
         let handle = winuser::GetForegroundWindow();
@@ -160,20 +725,514 @@ pub fn get_focused_window() -> Option<Window> {
     }
 }
 
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct Monitor {
     handle: MonitorHandle,
 }
 
+#[cfg(windows)]
 impl Monitor {
-    pub fn get_work_area(&self) -> Win32Result<Rect> {
+    pub fn from_id(id: MonitorId) -> Monitor {
+        Monitor {
+            handle: id.0 as MonitorHandle,
+        }
+    }
+
+    pub fn id(&self) -> MonitorId {
+        MonitorId(self.handle as isize)
+    }
+
+    fn get_monitor_info(&self) -> Result<winuser::MONITORINFO> {
         unsafe {
             let mut monitor_info: winuser::MONITORINFO = std::mem::zeroed();
             // u32 cast is safe, usize will be 32 bits or larger on all platforms we care about.
             monitor_info.cbSize = std::mem::size_of::<winuser::MONITORINFO>() as u32;
 
-            evaluate_fallible_winapi!(winuser::GetMonitorInfoW(self.handle, &mut monitor_info));
-            Ok(Rect::from_win32_rect(monitor_info.rcWork))
+            evaluate_fallible_winapi!(winuser::GetMonitorInfoW(self.handle, &mut monitor_info))
+                .map_err(|error| Error::Monitor {
+                    monitor: self.id(),
+                    error,
+                })?;
+            Ok(monitor_info)
         }
     }
+
+    pub fn get_work_area(&self) -> Result<Rect> {
+        Ok(Rect::from_win32_rect(self.get_monitor_info()?.rcWork))
+    }
+
+    pub fn get_info(&self) -> Result<MonitorInfo> {
+        let monitor_info = self.get_monitor_info()?;
+
+        Ok(MonitorInfo {
+            id: self.id(),
+            bounds: Rect::from_win32_rect(monitor_info.rcMonitor),
+            work_area: Rect::from_win32_rect(monitor_info.rcWork),
+            primary: monitor_info.dwFlags & winuser::MONITORINFOF_PRIMARY != 0,
+        })
+    }
+}
+
+/// Returns every top-level window, in z-order, topmost first.
+#[cfg(windows)]
+pub fn enumerate_windows() -> Result<Vec<Window>> {
+    unsafe extern "system" fn collect_window(
+        handle: WindowHandle,
+        windows: winapi::shared::minwindef::LPARAM,
+    ) -> i32 {
+        let windows = &mut *(windows as *mut Vec<Window>);
+        windows.push(Window { handle });
+        // Nonzero continues the enumeration.
+        1
+    }
+
+    let mut windows: Vec<Window> = Vec::new();
+
+    unsafe {
+        evaluate_fallible_winapi!(winuser::EnumWindows(
+            Some(collect_window),
+            &mut windows as *mut Vec<Window> as winapi::shared::minwindef::LPARAM
+        ))
+        .map_err(Error::Win32)?;
+    }
+
+    Ok(windows)
+}
+
+/// Returns every monitor attached to the desktop, in no particular order.
+#[cfg(windows)]
+pub fn enumerate_monitors() -> Result<Vec<Monitor>> {
+    unsafe extern "system" fn collect_monitor(
+        handle: MonitorHandle,
+        _: winapi::shared::windef::HDC,
+        _: winapi::shared::windef::LPRECT,
+        monitors: winapi::shared::minwindef::LPARAM,
+    ) -> i32 {
+        let monitors = &mut *(monitors as *mut Vec<Monitor>);
+        monitors.push(Monitor { handle });
+        // Nonzero continues the enumeration.
+        1
+    }
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+
+    unsafe {
+        evaluate_fallible_winapi!(winuser::EnumDisplayMonitors(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            Some(collect_monitor),
+            &mut monitors as *mut Vec<Monitor> as winapi::shared::minwindef::LPARAM
+        ))
+        .map_err(Error::Win32)?;
+    }
+
+    Ok(monitors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LayoutConfig, Metric};
+
+    // Rects recorded from a Notepad window on Windows 10, at 100% and 150% scaling.
+    const OUTER_100: Rect = Rect {
+        left: 953,
+        top: 0,
+        right: 1927,
+        bottom: 1047,
+    };
+    const FRAME_100: Rect = Rect {
+        left: 960,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+    const OUTER_150: Rect = Rect {
+        left: -11,
+        top: 0,
+        right: 1451,
+        bottom: 1571,
+    };
+    const FRAME_150: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1440,
+        bottom: 1560,
+    };
+
+    #[test]
+    fn borders_between_recorded_rects() {
+        assert_eq!(
+            Borders::between(&OUTER_100, &FRAME_100),
+            Borders {
+                left: 7,
+                top: 0,
+                right: 7,
+                bottom: 7,
+            }
+        );
+        assert_eq!(
+            Borders::between(&OUTER_150, &FRAME_150),
+            Borders {
+                left: 11,
+                top: 0,
+                right: 11,
+                bottom: 11,
+            }
+        );
+    }
+
+    #[test]
+    fn borders_convert_between_frames_and_outer_rects() {
+        for &(outer, frame) in &[(OUTER_100, FRAME_100), (OUTER_150, FRAME_150)] {
+            let borders = Borders::between(&outer, &frame);
+
+            assert_eq!(outer.shrink_by(&borders), frame);
+            assert_eq!(frame.expand_by(&borders), outer);
+        }
+
+        let none = Borders::default();
+        assert_eq!(FRAME_100.expand_by(&none), FRAME_100);
+        assert_eq!(FRAME_100.shrink_by(&none), FRAME_100);
+    }
+
+    #[test]
+    fn borders_from_mixed_coordinates_are_implausible() {
+        assert!(Borders::between(&OUTER_100, &FRAME_100).is_plausible());
+        assert!(Borders::default().is_plausible());
+
+        // The same window at 150%, with the outer rect scaled down to 96 DPI as Windows
+        // does for processes that are not DPI aware.
+        let virtualized = Rect::xyxy(-7, 0, 967, 1047);
+        assert!(!Borders::between(&virtualized, &FRAME_150).is_plausible());
+
+        let negative = Rect::xyxy(5, 0, 1435, 1555);
+        assert!(!Borders::between(&negative, &FRAME_150).is_plausible());
+    }
+
+    #[test]
+    fn grid_span_covers_cells() {
+        let area = Rect::xyxy(0, 0, 1200, 900);
+
+        assert_eq!(area.grid_span(3, 3, 0, 0, 1, 1), Rect::xyxy(0, 0, 400, 300));
+        assert_eq!(
+            area.grid_span(3, 3, 1, 2, 2, 1),
+            Rect::xyxy(400, 600, 1200, 900)
+        );
+        assert_eq!(area.grid_span(1, 1, 0, 0, 1, 1), area);
+
+        let offset = Rect::xyxy(100, 50, 1300, 950);
+        assert_eq!(
+            offset.grid_span(4, 2, 3, 1, 1, 1),
+            Rect::xyxy(1000, 500, 1300, 950)
+        );
+    }
+
+    #[test]
+    fn grid_span_adjacent_cells_share_edges() {
+        // 1000 does not divide into three, so the edges have to be rounded.
+        let area = Rect::xyxy(0, 0, 1000, 1000);
+        let cells: Vec<Rect> = (0..3).map(|x| area.grid_span(3, 1, x, 0, 1, 1)).collect();
+
+        assert_eq!(cells[0].left, 0);
+        assert_eq!(cells[0].right, cells[1].left);
+        assert_eq!(cells[1].right, cells[2].left);
+        assert_eq!(cells[2].right, 1000);
+    }
+
+    #[test]
+    fn grid_span_clamps_to_the_grid() {
+        let area = Rect::xyxy(0, 0, 1200, 900);
+
+        // Spans past the edge of the grid stop at it.
+        assert_eq!(
+            area.grid_span(3, 3, 2, 2, 5, 5),
+            Rect::xyxy(800, 600, 1200, 900)
+        );
+        // Cells past the grid are moved back onto it.
+        assert_eq!(
+            area.grid_span(3, 3, 7, 0, 1, 1),
+            Rect::xyxy(800, 0, 1200, 300)
+        );
+        // Empty grids and spans are treated as one cell.
+        assert_eq!(area.grid_span(0, 0, 0, 0, 0, 0), area);
+    }
+
+    #[test]
+    fn grid_span_does_not_overflow() {
+        let area = Rect::xyxy(0, 0, 1200, 900);
+
+        assert_eq!(
+            area.grid_span(4, 4, 1, 1, u32::MAX, 1),
+            Rect::xyxy(300, 225, 1200, 450)
+        );
+        assert_eq!(
+            area.grid_span(u32::MAX, u32::MAX, u32::MAX, u32::MAX, u32::MAX, u32::MAX)
+                .right,
+            1200
+        );
+    }
+
+    #[test]
+    fn resize_edge_grows_and_shrinks_each_edge() {
+        let rect = Rect::xyxy(100, 100, 500, 400);
+
+        assert_eq!(
+            rect.resize_edge(Direction::Up, 50, None, 1),
+            Rect::xyxy(100, 50, 500, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Down, 50, None, 1),
+            Rect::xyxy(100, 100, 500, 450)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Left, 50, None, 1),
+            Rect::xyxy(50, 100, 500, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Right, 50, None, 1),
+            Rect::xyxy(100, 100, 550, 400)
+        );
+
+        assert_eq!(
+            rect.resize_edge(Direction::Up, -50, None, 1),
+            Rect::xyxy(100, 150, 500, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Down, -50, None, 1),
+            Rect::xyxy(100, 100, 500, 350)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Left, -50, None, 1),
+            Rect::xyxy(150, 100, 500, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Right, -50, None, 1),
+            Rect::xyxy(100, 100, 450, 400)
+        );
+    }
+
+    #[test]
+    fn resize_edge_moves_both_edges_of_a_corner() {
+        let rect = Rect::xyxy(100, 100, 500, 400);
+
+        assert_eq!(
+            rect.resize_edge(Direction::UpLeft, 50, None, 1),
+            Rect::xyxy(50, 50, 500, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::UpRight, 50, None, 1),
+            Rect::xyxy(100, 50, 550, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::DownLeft, -50, None, 1),
+            Rect::xyxy(150, 100, 500, 350)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::DownRight, -50, None, 1),
+            Rect::xyxy(100, 100, 450, 350)
+        );
+    }
+
+    #[test]
+    fn resize_edge_stops_at_the_limit() {
+        let limit = Rect::xyxy(0, 0, 1920, 1040);
+        let rect = Rect::xyxy(100, 100, 500, 400);
+
+        assert_eq!(
+            rect.resize_edge(Direction::Left, 500, Some(&limit), 1),
+            Rect::xyxy(0, 100, 500, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::DownRight, 5000, Some(&limit), 1),
+            Rect::xyxy(100, 100, 1920, 1040)
+        );
+        // Without a limit, edges go wherever they are sent.
+        assert_eq!(
+            rect.resize_edge(Direction::Up, 500, None, 1),
+            Rect::xyxy(100, -400, 500, 400)
+        );
+
+        // An edge that is already past the limit is not pulled back.
+        let overhanging = Rect::xyxy(-20, 100, 500, 400);
+        assert_eq!(
+            overhanging.resize_edge(Direction::Left, 10, Some(&limit), 1),
+            overhanging
+        );
+        assert_eq!(
+            overhanging.resize_edge(Direction::Left, -10, Some(&limit), 1),
+            Rect::xyxy(-10, 100, 500, 400)
+        );
+    }
+
+    #[test]
+    fn resize_edge_keeps_a_minimum_size() {
+        use crate::action::MINIMUM_WINDOW_SIZE;
+
+        let rect = Rect::xyxy(100, 100, 500, 400);
+
+        assert_eq!(
+            rect.resize_edge(Direction::Right, -1000, None, MINIMUM_WINDOW_SIZE),
+            Rect::xyxy(100, 100, 100 + MINIMUM_WINDOW_SIZE, 400)
+        );
+        assert_eq!(
+            rect.resize_edge(Direction::Up, -1000, None, MINIMUM_WINDOW_SIZE),
+            Rect::xyxy(100, 400 - MINIMUM_WINDOW_SIZE, 500, 400)
+        );
+        // Rects can always shrink to a single pixel, but never turn inside out.
+        assert_eq!(
+            rect.resize_edge(Direction::Left, -1000, None, 1),
+            Rect::xyxy(499, 100, 500, 400)
+        );
+
+        // A rect that is already smaller than the minimum keeps its size.
+        let small = Rect::xyxy(0, 0, 50, 50);
+        assert_eq!(
+            small.resize_edge(Direction::Down, -10, None, MINIMUM_WINDOW_SIZE),
+            small
+        );
+    }
+
+    #[test]
+    fn centered_rects_are_in_the_middle_of_the_area() {
+        let area = Rect::xyxy(1920, 40, 3840, 1080);
+
+        assert_eq!(area.centered(960, 520), Rect::xyxy(2400, 300, 3360, 820));
+        assert_eq!(area.centered(1920, 1040), area);
+    }
+
+    #[test]
+    fn centered_rects_are_clamped_to_the_area() {
+        let area = Rect::xyxy(1920, 40, 3840, 1080);
+
+        assert_eq!(area.centered(5000, 520), Rect::xyxy(1920, 300, 3840, 820));
+        assert_eq!(area.centered(960, 5000), Rect::xyxy(2400, 40, 3360, 1080));
+        assert_eq!(area.centered(-10, -10).wh(), (0, 0));
+    }
+
+    #[test]
+    fn almost_maximized_rects_keep_a_percent_margin() {
+        let area = Rect::xyxy(1920, 40, 3840, 1080);
+        let margin = Metric::Percent(0.05);
+        let (width, height) = area.wh();
+
+        assert_eq!(
+            area.inset(margin.resolve(width), margin.resolve(height)),
+            Rect::xyxy(2016, 92, 3744, 1028)
+        );
+    }
+
+    const WORK_AREA: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+
+    fn uniform_gaps(outer: i32, inner: i32) -> Gaps {
+        Gaps {
+            outer_horizontal: outer,
+            outer_vertical: outer,
+            inner_horizontal: inner,
+            inner_vertical: inner,
+        }
+    }
+
+    /// Places a slice of the work area the way a push does.
+    fn push(direction: Direction, gaps: &Gaps) -> Rect {
+        let area = gaps.placement_area(&WORK_AREA);
+        area.slice_rect(direction, 2.0)
+            .apply_inner_gaps(&area, gaps)
+    }
+
+    #[test]
+    fn halves_are_one_inner_gap_apart() {
+        let gaps = uniform_gaps(10, 20);
+
+        let left = push(Direction::Left, &gaps);
+        let right = push(Direction::Right, &gaps);
+        assert_eq!(left, Rect::xyxy(10, 10, 950, 1030));
+        assert_eq!(right, Rect::xyxy(970, 10, 1910, 1030));
+        assert_eq!(right.left - left.right, 20);
+
+        let top = push(Direction::Up, &gaps);
+        let bottom = push(Direction::Down, &gaps);
+        assert_eq!(top, Rect::xyxy(10, 10, 1910, 510));
+        assert_eq!(bottom, Rect::xyxy(10, 530, 1910, 1030));
+        assert_eq!(bottom.top - top.bottom, 20);
+    }
+
+    #[test]
+    fn outer_gaps_are_kept_around_the_work_area() {
+        let gaps = Gaps {
+            outer_horizontal: 30,
+            outer_vertical: 15,
+            inner_horizontal: 0,
+            inner_vertical: 0,
+        };
+
+        assert_eq!(
+            gaps.placement_area(&WORK_AREA),
+            Rect::xyxy(30, 15, 1890, 1025)
+        );
+        assert_eq!(push(Direction::Left, &gaps), Rect::xyxy(30, 15, 960, 1025));
+        // Edges on the boundary of the area are left alone, even with inner gaps.
+        assert_eq!(
+            WORK_AREA.apply_inner_gaps(&WORK_AREA, &uniform_gaps(0, 40)),
+            WORK_AREA
+        );
+    }
+
+    #[test]
+    fn odd_inner_gaps_are_split_between_the_two_sides() {
+        let gaps = uniform_gaps(0, 7);
+
+        let left = push(Direction::Left, &gaps);
+        let right = push(Direction::Right, &gaps);
+        assert_eq!(left.right, 960 - 3);
+        assert_eq!(right.left, 960 + 4);
+        assert_eq!(right.left - left.right, 7);
+    }
+
+    #[test]
+    fn gaps_larger_than_the_rect_collapse_it() {
+        let area = Rect::xyxy(0, 0, 100, 100);
+        let middle = Rect::xyxy(40, 40, 60, 60);
+
+        let placed = middle.apply_inner_gaps(&area, &uniform_gaps(0, 100));
+        assert_eq!(placed.wh(), (0, 0));
+        assert!(placed.left <= placed.right && placed.top <= placed.bottom);
+
+        // Outer gaps stop at the middle of the work area.
+        let area = WORK_AREA.inset(5000, 5000);
+        assert_eq!(area.wh(), (0, 0));
+        assert_eq!(area.center(), WORK_AREA.center());
+    }
+
+    #[test]
+    fn percent_gaps_resolve_against_the_work_area() {
+        let layout = LayoutConfig {
+            outer_gap: Metric::Percent(0.01),
+            inner_gap: Metric::Percent(0.02),
+        };
+
+        // Horizontal gaps are of the width and vertical ones of the height, and fractions of
+        // a pixel are dropped.
+        assert_eq!(
+            layout.resolve(&WORK_AREA),
+            Gaps {
+                outer_horizontal: 19,
+                outer_vertical: 10,
+                inner_horizontal: 38,
+                inner_vertical: 20,
+            }
+        );
+
+        let layout = LayoutConfig {
+            outer_gap: Metric::Absolute(8.0),
+            inner_gap: Metric::Absolute(12.0),
+        };
+        assert_eq!(layout.resolve(&WORK_AREA), uniform_gaps(8, 12));
+    }
 }
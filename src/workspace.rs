@@ -0,0 +1,282 @@
+//! Bookkeeping for workspaces: groups of windows on a monitor, of which only one is shown
+//! at a time. This only decides which windows to hide and show; the executor does the
+//! hiding and showing.
+use std::collections::HashMap;
+
+use crate::backend::{MonitorId, WindowId};
+
+/// The workspace that each monitor starts on, and that windows are first put in.
+pub const DEFAULT_WORKSPACE: u32 = 1;
+
+/// The windows to hide and show to carry out a workspace change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transition {
+    pub hide: Vec<WindowId>,
+    pub show: Vec<WindowId>,
+}
+
+impl Transition {
+    pub fn is_empty(&self) -> bool {
+        self.hide.is_empty() && self.show.is_empty()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Placement {
+    monitor: MonitorId,
+    workspace: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Workspaces {
+    placements: HashMap<WindowId, Placement>,
+    /// The workspace shown on each monitor, for monitors that have switched away from
+    /// [`DEFAULT_WORKSPACE`].
+    active: HashMap<MonitorId, u32>,
+}
+
+impl Workspaces {
+    pub fn new() -> Workspaces {
+        Workspaces::default()
+    }
+
+    /// Returns the workspace shown on a monitor.
+    pub fn active(&self, monitor: MonitorId) -> u32 {
+        self.active
+            .get(&monitor)
+            .copied()
+            .unwrap_or(DEFAULT_WORKSPACE)
+    }
+
+    pub fn workspace_of(&self, window: WindowId) -> Option<u32> {
+        self.placements
+            .get(&window)
+            .map(|placement| placement.workspace)
+    }
+
+    /// Records that a window is visible on a monitor. Visible windows belong to the
+    /// monitor's active workspace, so this also picks up windows that were opened, moved to
+    /// another monitor, or shown again by their application since whimsy last looked.
+    pub fn observe(&mut self, window: WindowId, monitor: MonitorId) {
+        let workspace = self.active(monitor);
+        self.placements
+            .insert(window, Placement { monitor, workspace });
+    }
+
+    /// Shows a different workspace on a monitor.
+    pub fn switch(&mut self, monitor: MonitorId, workspace: u32) -> Transition {
+        let previous = self.active(monitor);
+        if previous == workspace {
+            return Transition::default();
+        }
+
+        self.active.insert(monitor, workspace);
+
+        let on_workspace = |workspace: u32| {
+            let mut windows: Vec<WindowId> = self
+                .placements
+                .iter()
+                .filter(|(_, placement)| {
+                    placement.monitor == monitor && placement.workspace == workspace
+                })
+                .map(|(&window, _)| window)
+                .collect();
+            windows.sort();
+            windows
+        };
+
+        Transition {
+            hide: on_workspace(previous),
+            show: on_workspace(workspace),
+        }
+    }
+
+    /// Moves a window to a workspace on a monitor, hiding it if that workspace is not the
+    /// one being shown.
+    pub fn move_window(
+        &mut self,
+        window: WindowId,
+        monitor: MonitorId,
+        workspace: u32,
+    ) -> Transition {
+        let active = self.active(monitor);
+        let previous = self
+            .placements
+            .insert(window, Placement { monitor, workspace });
+        let was_visible =
+            previous.is_none_or(|previous| previous.workspace == self.active(previous.monitor));
+        let is_visible = workspace == active;
+
+        match (was_visible, is_visible) {
+            (true, false) => Transition {
+                hide: vec![window],
+                show: Vec::new(),
+            },
+            (false, true) => Transition {
+                hide: Vec::new(),
+                show: vec![window],
+            },
+            _ => Transition::default(),
+        }
+    }
+
+    /// Returns every window that is on a workspace that is not being shown.
+    pub fn hidden(&self) -> Vec<WindowId> {
+        let mut windows: Vec<WindowId> = self
+            .placements
+            .iter()
+            .filter(|(_, placement)| placement.workspace != self.active(placement.monitor))
+            .map(|(&window, _)| window)
+            .collect();
+        windows.sort();
+        windows
+    }
+
+    /// Forgets every window for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(WindowId) -> bool) {
+        self.placements.retain(|&window, _| keep(window));
+    }
+
+    /// Forgets every window and returns every monitor to [`DEFAULT_WORKSPACE`].
+    pub fn clear(&mut self) {
+        self.placements.clear();
+        self.active.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: MonitorId = MonitorId(1);
+    const RIGHT: MonitorId = MonitorId(2);
+
+    fn windows(ids: &[isize]) -> Vec<WindowId> {
+        ids.iter().copied().map(WindowId).collect()
+    }
+
+    /// Windows 1 and 2 on the left monitor, and 3 on the right.
+    fn workspaces() -> Workspaces {
+        let mut workspaces = Workspaces::new();
+        workspaces.observe(WindowId(1), LEFT);
+        workspaces.observe(WindowId(2), LEFT);
+        workspaces.observe(WindowId(3), RIGHT);
+        workspaces
+    }
+
+    #[test]
+    fn monitors_start_on_the_default_workspace() {
+        let workspaces = workspaces();
+
+        assert_eq!(workspaces.active(LEFT), DEFAULT_WORKSPACE);
+        assert_eq!(
+            workspaces.workspace_of(WindowId(1)),
+            Some(DEFAULT_WORKSPACE)
+        );
+        assert_eq!(workspaces.workspace_of(WindowId(4)), None);
+        assert!(workspaces.hidden().is_empty());
+    }
+
+    #[test]
+    fn switch_hides_the_old_workspace_and_shows_the_new_one() {
+        let mut workspaces = workspaces();
+
+        let transition = workspaces.switch(LEFT, 2);
+        assert_eq!(
+            transition,
+            Transition {
+                hide: windows(&[1, 2]),
+                show: Vec::new(),
+            }
+        );
+        assert_eq!(workspaces.active(LEFT), 2);
+        // Other monitors keep their workspace.
+        assert_eq!(workspaces.active(RIGHT), DEFAULT_WORKSPACE);
+
+        // Windows opened now belong to the workspace being shown.
+        workspaces.observe(WindowId(4), LEFT);
+        assert_eq!(workspaces.workspace_of(WindowId(4)), Some(2));
+
+        assert_eq!(
+            workspaces.switch(LEFT, DEFAULT_WORKSPACE),
+            Transition {
+                hide: windows(&[4]),
+                show: windows(&[1, 2]),
+            }
+        );
+    }
+
+    #[test]
+    fn switch_to_the_shown_workspace_does_nothing() {
+        let mut workspaces = workspaces();
+
+        assert!(workspaces.switch(LEFT, DEFAULT_WORKSPACE).is_empty());
+        assert_eq!(workspaces.active(LEFT), DEFAULT_WORKSPACE);
+    }
+
+    #[test]
+    fn move_window_hides_windows_moved_off_the_shown_workspace() {
+        let mut workspaces = workspaces();
+
+        assert_eq!(
+            workspaces.move_window(WindowId(1), LEFT, 2),
+            Transition {
+                hide: windows(&[1]),
+                show: Vec::new(),
+            }
+        );
+        assert_eq!(workspaces.workspace_of(WindowId(1)), Some(2));
+
+        // Moving between hidden workspaces keeps it hidden.
+        assert!(workspaces.move_window(WindowId(1), LEFT, 3).is_empty());
+
+        // Switching to its workspace shows it again, with nothing else.
+        assert_eq!(
+            workspaces.switch(LEFT, 3),
+            Transition {
+                hide: windows(&[2]),
+                show: windows(&[1]),
+            }
+        );
+    }
+
+    #[test]
+    fn move_window_shows_windows_moved_onto_the_shown_workspace() {
+        let mut workspaces = workspaces();
+        workspaces.switch(LEFT, 2);
+
+        assert_eq!(
+            workspaces.move_window(WindowId(1), LEFT, 2),
+            Transition {
+                hide: Vec::new(),
+                show: windows(&[1]),
+            }
+        );
+        assert!(workspaces.move_window(WindowId(1), LEFT, 2).is_empty());
+
+        // Windows whimsy has not seen before are visible until moved away.
+        assert_eq!(
+            workspaces.move_window(WindowId(5), RIGHT, 4),
+            Transition {
+                hide: windows(&[5]),
+                show: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn hidden_lists_windows_on_workspaces_that_are_not_shown() {
+        let mut workspaces = workspaces();
+        workspaces.move_window(WindowId(3), RIGHT, 2);
+        workspaces.switch(LEFT, 2);
+
+        assert_eq!(workspaces.hidden(), windows(&[1, 2, 3]));
+
+        workspaces.retain(|window| window != WindowId(2));
+        assert_eq!(workspaces.hidden(), windows(&[1, 3]));
+
+        workspaces.clear();
+        assert!(workspaces.hidden().is_empty());
+        assert_eq!(workspaces.active(LEFT), DEFAULT_WORKSPACE);
+    }
+}